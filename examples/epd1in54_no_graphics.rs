@@ -4,7 +4,7 @@ use embedded_hal::prelude::*;
 use epd_waveshare::{epd1in54::Epd1in54, prelude::*};
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
-    sysfs_gpio::Direction,
+    sysfs_gpio::{self, Direction},
     Delay, Pin, Spidev,
 };
 
@@ -12,10 +12,10 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), EpdError<std::io::Error, sysfs_gpio::Error>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
-    let mut spi = Spidev::open("/dev/spidev0.0")?;
+    let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
     let options = SpidevOptions::new()
         .bits_per_word(8)
         .max_speed_hz(4_000_000)
@@ -15,7 +15,7 @@ use epd_waveshare::{
 };
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
-    sysfs_gpio::Direction,
+    sysfs_gpio::{self, Direction},
     Delay, Pin, Spidev,
 };
 
@@ -24,7 +24,7 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), EpdError<std::io::Error, sysfs_gpio::Error>> {
     // Configure SPI
     // Settings are taken from
     let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
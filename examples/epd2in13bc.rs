@@ -15,7 +15,7 @@ use epd_waveshare::{
 };
 use linux_embedded_hal::{
     spidev::{self, SpidevOptions},
-    sysfs_gpio::Direction,
+    sysfs_gpio::{self, Direction},
     Delay, Pin, Spidev,
 };
 
@@ -34,7 +34,7 @@ use linux_embedded_hal::{
 //
 // after finishing, put the display to sleep
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), EpdError<std::io::Error, sysfs_gpio::Error>> {
     let busy = Pin::new(24); // GPIO 24, board J-18
     busy.export().expect("busy export");
     while !busy.is_exported() {}
@@ -0,0 +1,496 @@
+//! Choosing the display model at runtime
+//!
+//! Every driver is its own type, so code supporting several panels would need to be generic over
+//! all of them. [`AnyEpd`] wraps one of the supported drivers, picked with an [`EpdModel`] that
+//! can be read from a configuration, e.g. an id stored in an EEPROM, and offers the common
+//! [`WaveshareDisplay`] operations.
+//!
+//! All supported models are black and white displays, see [`EpdModel::color_type`], drawn on with
+//! a [`VarDisplay`] of the right size.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{any_epd::{AnyEpd, EpdModel}, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!# let model_id = 1;
+//!
+//!let model = EpdModel::from_id(model_id).unwrap_or(EpdModel::Epd2in9V2);
+//!let mut epd = AnyEpd::new(model, &mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!let mut buffer = [0u8; EpdModel::MAX_BUFFER_LEN];
+//!let mut display = epd.display(&mut buffer).unwrap();
+//!display.clear(Color::White).ok();
+//!let _ = Line::new(Point::new(0, 0), Point::new(100, 100))
+//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!    .draw(&mut display);
+//!
+//!if epd.capabilities().partial_refresh {
+//!    epd.set_refresh_mode(&mut spi, &mut delay, RefreshMode::Partial)?;
+//!}
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::EpdError;
+use crate::traits::{Capabilities, RefreshLut, RefreshMode, WaveshareDisplay};
+use crate::{epd2in9_v2, epd4in2, epd7in5_v2};
+
+#[cfg(feature = "graphics")]
+use crate::graphics::{VarDisplay, VarDisplayError};
+
+/// Color type a model is drawn with, see [`EpdModel::color_type`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorKind {
+    /// Black and white, drawn with [`Color`]
+    Color,
+}
+
+/// Display models supported by [`AnyEpd`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpdModel {
+    /// [`epd2in9_v2`]
+    Epd2in9V2,
+    /// [`epd4in2`]
+    Epd4in2,
+    /// [`epd7in5_v2`]
+    Epd7in5V2,
+}
+
+impl EpdModel {
+    /// All supported models
+    pub const ALL: [EpdModel; 3] = [EpdModel::Epd2in9V2, EpdModel::Epd4in2, EpdModel::Epd7in5V2];
+
+    /// Size of the largest frame buffer of all models, enough for a buffer of any of them
+    pub const MAX_BUFFER_LEN: usize = EpdModel::max_buffer_len();
+
+    /// Looks up a model by the id returned by [`id`](EpdModel::id)
+    pub fn from_id(id: u8) -> Option<EpdModel> {
+        EpdModel::ALL.into_iter().find(|model| model.id() == id)
+    }
+
+    /// Number identifying the model, e.g. to store it in a configuration
+    pub const fn id(self) -> u8 {
+        match self {
+            EpdModel::Epd2in9V2 => 0,
+            EpdModel::Epd4in2 => 1,
+            EpdModel::Epd7in5V2 => 2,
+        }
+    }
+
+    /// Looks up a model by the name returned by [`name`](EpdModel::name)
+    pub fn from_name(name: &str) -> Option<EpdModel> {
+        EpdModel::ALL.into_iter().find(|model| model.name() == name)
+    }
+
+    /// Name of the driver module of the model, e.g. `"epd4in2"`
+    pub const fn name(self) -> &'static str {
+        match self {
+            EpdModel::Epd2in9V2 => "epd2in9_v2",
+            EpdModel::Epd4in2 => "epd4in2",
+            EpdModel::Epd7in5V2 => "epd7in5_v2",
+        }
+    }
+
+    /// Width of the display in pixels
+    pub const fn width(self) -> u32 {
+        match self {
+            EpdModel::Epd2in9V2 => epd2in9_v2::WIDTH,
+            EpdModel::Epd4in2 => epd4in2::WIDTH,
+            EpdModel::Epd7in5V2 => epd7in5_v2::WIDTH,
+        }
+    }
+
+    /// Height of the display in pixels
+    pub const fn height(self) -> u32 {
+        match self {
+            EpdModel::Epd2in9V2 => epd2in9_v2::HEIGHT,
+            EpdModel::Epd4in2 => epd4in2::HEIGHT,
+            EpdModel::Epd7in5V2 => epd7in5_v2::HEIGHT,
+        }
+    }
+
+    /// Length of a full frame buffer
+    pub const fn buffer_len(self) -> usize {
+        buffer_len(self.width() as usize, self.height() as usize)
+    }
+
+    // Largest `buffer_len` of all models, a loop because iterators aren't const
+    const fn max_buffer_len() -> usize {
+        let mut max = 0;
+        let mut i = 0;
+        while i < EpdModel::ALL.len() {
+            let len = EpdModel::ALL[i].buffer_len();
+            if len > max {
+                max = len;
+            }
+            i += 1;
+        }
+        max
+    }
+
+    /// Color type the display is drawn with, which is also the one of
+    /// [`AnyEpd::display`]
+    pub const fn color_type(self) -> ColorKind {
+        match self {
+            EpdModel::Epd2in9V2 | EpdModel::Epd4in2 | EpdModel::Epd7in5V2 => ColorKind::Color,
+        }
+    }
+}
+
+/// One of the drivers of [`EpdModel`], selected at runtime
+///
+/// The drivers stay reachable by matching on the variants, e.g. for [`QuickRefresh`] of the
+/// Epd 2in9 V2 and 4in2.
+///
+/// [`QuickRefresh`]: crate::traits::QuickRefresh
+pub enum AnyEpd<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Epd 2in9 V2 driver
+    Epd2in9V2(epd2in9_v2::Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// Epd 4in2 driver
+    Epd4in2(epd4in2::Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>),
+    /// Epd 7in5 V2 driver
+    Epd7in5V2(epd7in5_v2::Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>),
+}
+
+// Runs `$body` with `$epd` bound to the wrapped driver
+macro_rules! dispatch {
+    ($self:expr, $epd:ident => $body:expr) => {
+        match $self {
+            AnyEpd::Epd2in9V2($epd) => $body,
+            AnyEpd::Epd4in2($epd) => $body,
+            AnyEpd::Epd7in5V2($epd) => $body,
+        }
+    };
+}
+
+// Evaluates to the associated constant `$name` of the wrapped driver
+macro_rules! driver_const {
+    ($module:ident::$driver:ident, $name:ident) => {
+        <$module::$driver<SPI, CS, BUSY, DC, RST, DELAY> as WaveshareDisplay<
+            SPI,
+            CS,
+            BUSY,
+            DC,
+            RST,
+            DELAY,
+        >>::$name
+    };
+    ($self:expr, $name:ident) => {
+        match $self {
+            AnyEpd::Epd2in9V2(_) => driver_const!(epd2in9_v2::Epd2in9, $name),
+            AnyEpd::Epd4in2(_) => driver_const!(epd4in2::Epd4in2, $name),
+            AnyEpd::Epd7in5V2(_) => driver_const!(epd7in5_v2::Epd7in5, $name),
+        }
+    };
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> AnyEpd<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    /// Creates and initialises the driver of `model`, see [`WaveshareDisplay::new`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        model: EpdModel,
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
+        Ok(match model {
+            EpdModel::Epd2in9V2 => AnyEpd::Epd2in9V2(epd2in9_v2::Epd2in9::new(
+                spi, cs, busy, dc, rst, delay, delay_us,
+            )?),
+            EpdModel::Epd4in2 => AnyEpd::Epd4in2(epd4in2::Epd4in2::new(
+                spi, cs, busy, dc, rst, delay, delay_us,
+            )?),
+            EpdModel::Epd7in5V2 => AnyEpd::Epd7in5V2(epd7in5_v2::Epd7in5::new(
+                spi, cs, busy, dc, rst, delay, delay_us,
+            )?),
+        })
+    }
+
+    /// The model of the wrapped driver
+    pub fn model(&self) -> EpdModel {
+        match self {
+            AnyEpd::Epd2in9V2(_) => EpdModel::Epd2in9V2,
+            AnyEpd::Epd4in2(_) => EpdModel::Epd4in2,
+            AnyEpd::Epd7in5V2(_) => EpdModel::Epd7in5V2,
+        }
+    }
+
+    /// Color type the display is drawn with, see [`EpdModel::color_type`]
+    pub fn color_type(&self) -> ColorKind {
+        self.model().color_type()
+    }
+
+    /// Features the display supports, see [`WaveshareDisplay::CAPABILITIES`]
+    pub fn capabilities(&self) -> Capabilities {
+        driver_const!(self, CAPABILITIES)
+    }
+
+    /// Length of a full frame buffer, see [`WaveshareDisplay::BUFFER_LEN`]
+    pub fn buffer_len(&self) -> usize {
+        driver_const!(self, BUFFER_LEN)
+    }
+
+    /// Creates a [`VarDisplay`] of the size of the display on `buffer`
+    ///
+    /// `buffer` needs to hold at least [`buffer_len`](AnyEpd::buffer_len) bytes,
+    /// [`EpdModel::MAX_BUFFER_LEN`] is enough for every model.
+    #[cfg(feature = "graphics")]
+    pub fn display<'a>(
+        &self,
+        buffer: &'a mut [u8],
+    ) -> Result<VarDisplay<'a, Color>, VarDisplayError> {
+        VarDisplay::new(self.width(), self.height(), buffer, false)
+    }
+
+    /// See [`WaveshareDisplay::sleep`]
+    pub fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.sleep(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::wake_up`]
+    pub fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.wake_up(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::set_background_color`]
+    pub fn set_background_color(&mut self, color: Color) {
+        dispatch!(self, epd => epd.set_background_color(color))
+    }
+
+    /// See [`WaveshareDisplay::background_color`]
+    pub fn background_color(&self) -> &Color {
+        dispatch!(self, epd => epd.background_color())
+    }
+
+    /// See [`WaveshareDisplay::set_busy_timeout`]
+    pub fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        dispatch!(self, epd => epd.set_busy_timeout(max_ticks))
+    }
+
+    /// See [`WaveshareDisplay::set_max_transfer_size`]
+    pub fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        dispatch!(self, epd => epd.set_max_transfer_size(max_bytes))
+    }
+
+    /// See [`WaveshareDisplay::width`]
+    pub fn width(&self) -> u32 {
+        dispatch!(self, epd => epd.width())
+    }
+
+    /// See [`WaveshareDisplay::height`]
+    pub fn height(&self) -> u32 {
+        dispatch!(self, epd => epd.height())
+    }
+
+    /// See [`WaveshareDisplay::update_frame`]
+    pub fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.update_frame(spi, buffer, delay))
+    }
+
+    /// See [`WaveshareDisplay::update_partial_frame`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.update_partial_frame(spi, delay, buffer, x, y, width, height))
+    }
+
+    /// See [`WaveshareDisplay::display_frame`]
+    pub fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.display_frame(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::start_refresh`]
+    pub fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.start_refresh(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::finish_refresh`]
+    pub fn finish_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.finish_refresh(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::is_busy`]
+    pub fn is_busy(&mut self, spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.is_busy(spi))
+    }
+
+    /// See [`WaveshareDisplay::update_and_display_frame`]
+    pub fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.update_and_display_frame(spi, buffer, delay))
+    }
+
+    /// See [`WaveshareDisplay::clear_frame`]
+    pub fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.clear_frame(spi, delay))
+    }
+
+    /// See [`WaveshareDisplay::set_lut`]
+    pub fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.set_lut(spi, delay, refresh_rate))
+    }
+
+    /// See [`WaveshareDisplay::supports_refresh_mode`]
+    pub fn supports_refresh_mode(&self, mode: RefreshMode) -> bool {
+        dispatch!(self, epd => epd.supports_refresh_mode(mode))
+    }
+
+    /// See [`WaveshareDisplay::set_refresh_mode`]
+    pub fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.set_refresh_mode(spi, delay, mode))
+    }
+
+    /// See [`WaveshareDisplay::wait_until_idle`]
+    pub fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        dispatch!(self, epd => epd.wait_until_idle(spi, delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_ids_and_names() {
+        for model in EpdModel::ALL {
+            assert_eq!(EpdModel::from_id(model.id()), Some(model));
+            assert_eq!(EpdModel::from_name(model.name()), Some(model));
+            assert!(model.buffer_len() <= EpdModel::MAX_BUFFER_LEN);
+        }
+        assert_eq!(EpdModel::MAX_BUFFER_LEN, EpdModel::Epd7in5V2.buffer_len());
+        assert_eq!(EpdModel::from_id(3), None);
+        assert_eq!(EpdModel::from_name("epd4in2_v2"), None);
+        assert_eq!(EpdModel::Epd4in2.buffer_len(), 400 / 8 * 300);
+        assert_eq!(EpdModel::Epd4in2.color_type(), ColorKind::Color);
+    }
+
+    #[cfg(all(feature = "graphics", feature = "simulator"))]
+    #[test]
+    fn drive_a_model() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use embedded_graphics::{
+            prelude::*,
+            primitives::{Line, PrimitiveStyle},
+        };
+
+        let model = EpdModel::Epd4in2;
+        let sim = Simulator::new(Controller::Uc81xx, model.width(), model.height());
+        let mut spi = sim.spi();
+        let mut epd = AnyEpd::new(
+            model,
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(epd.model(), model);
+        assert_eq!(epd.color_type(), ColorKind::Color);
+
+        let mut buffer = [0u8; EpdModel::MAX_BUFFER_LEN];
+        let mut display = epd.display(&mut buffer).unwrap();
+        assert_eq!(display.size(), Size::new(400, 300));
+        assert_eq!(display.buffer().len(), epd.buffer_len());
+
+        display.clear(Color::White).ok();
+        let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+            .draw(&mut display);
+        epd.update_and_display_frame(&mut spi, display.buffer(), &mut SimDelay)
+            .unwrap();
+
+        assert_eq!(sim.refreshes(), 1);
+        assert_eq!(sim.shown(1), display.buffer());
+        assert_eq!(sim.shown(1)[..2], [0x00, 0xFF]);
+    }
+}
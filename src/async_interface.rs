@@ -0,0 +1,368 @@
+use crate::error::EpdError;
+use crate::sequence::{Busy, Step};
+use crate::traits::Command;
+use core::marker::PhantomData;
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+/// Size of the stack buffer used to repeat a data byte
+const REPEAT_CHUNK: usize = 64;
+
+/// Time between two status commands while waiting for controllers that need them, the same as
+/// the default `delay_us` of the blocking drivers
+const STATUS_POLL_US: u32 = 10_000;
+
+/// The async counterpart of `DisplayInterface`
+///
+/// The chip select is handled by the [`SpiDevice`], so there's no CS pin.
+pub(crate) struct AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY> {
+    /// SPI
+    _spi: PhantomData<SPI>,
+    /// DELAY
+    _delay: PhantomData<DELAY>,
+    /// Waits for the display to be ready
+    busy: BUSY,
+    /// Data/Command Control Pin (High for data, Low for command)
+    dc: DC,
+    /// Pin for Resetting
+    rst: RST,
+    /// number of BUSY polls before giving up, `None` waits forever
+    busy_timeout: Option<u32>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    /// Creates a new `AsyncDisplayInterface` struct
+    pub fn new(busy: BUSY, dc: DC, rst: RST) -> Self {
+        AsyncDisplayInterface {
+            _spi: PhantomData,
+            _delay: PhantomData,
+            busy,
+            dc,
+            rst,
+            busy_timeout: None,
+        }
+    }
+
+    /// Sets the maximum number of BUSY polls, `STATUS_POLL_US` apart, before returning
+    /// [`EpdError::BusyTimeout`]
+    pub(crate) fn set_busy_timeout(&mut self, max_polls: Option<u32>) {
+        self.busy_timeout = max_polls;
+    }
+
+    /// Sends a [Command](Command)
+    pub(crate) async fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        // low for commands
+        self.dc.set_low().map_err(EpdError::Pin)?;
+        spi.write(&[command.address()]).await.map_err(EpdError::Spi)
+    }
+
+    /// Sends data in a single transfer
+    pub(crate) async fn data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        // high for data
+        self.dc.set_high().map_err(EpdError::Pin)?;
+        spi.write(data).await.map_err(EpdError::Spi)
+    }
+
+    /// Sends a [Command](Command) and the data belonging to it
+    pub(crate) async fn cmd_with_data<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.cmd(spi, command).await?;
+        self.data(spi, data).await
+    }
+
+    /// Sends the same byte of data multiple times
+    pub(crate) async fn data_x_times(
+        &mut self,
+        spi: &mut SPI,
+        val: u8,
+        repetitions: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        // high for data
+        self.dc.set_high().map_err(EpdError::Pin)?;
+        let chunk = [val; REPEAT_CHUNK];
+        let mut remaining = repetitions as usize;
+        while remaining > 0 {
+            let len = remaining.min(REPEAT_CHUNK);
+            spi.write(&chunk[..len]).await.map_err(EpdError::Spi)?;
+            remaining -= len;
+        }
+        Ok(())
+    }
+
+    /// Waits for the BUSY line to signal idle, the way described by `busy`
+    ///
+    /// Controllers that need a status command only update the BUSY line when they get it, so
+    /// like the blocking `wait_until_idle_with_cmd`, the command is sent again until the line
+    /// is idle. The other controllers await the idle level, unless a busy timeout is set, in
+    /// which case the line is polled like for the status command.
+    pub(crate) async fn wait_until_idle<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        busy: Busy<T>,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        match (busy.status_command, self.busy_timeout) {
+            (None, None) if busy.is_busy_low => {
+                self.busy.wait_for_high().await.map_err(EpdError::Pin)
+            }
+            (None, None) => self.busy.wait_for_low().await.map_err(EpdError::Pin),
+            (status_command, _) => {
+                let mut polls = 0;
+                loop {
+                    if let Some(status_command) = status_command {
+                        self.cmd(spi, status_command).await?;
+                    }
+                    if self.busy.is_high().map_err(EpdError::Pin)? == busy.is_busy_low {
+                        return Ok(());
+                    }
+                    match self.busy_timeout {
+                        Some(max_polls) if polls >= max_polls => return Err(EpdError::BusyTimeout),
+                        _ => polls += 1,
+                    }
+                    delay.delay_us(STATUS_POLL_US).await;
+                }
+            }
+        }
+    }
+
+    /// Resets the device, see `DisplayInterface::reset`
+    pub(crate) async fn reset(
+        &mut self,
+        delay: &mut DELAY,
+        initial_delay: u32,
+        duration: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.rst.set_high().map_err(EpdError::Pin)?;
+        delay.delay_us(initial_delay).await;
+
+        self.rst.set_low().map_err(EpdError::Pin)?;
+        delay.delay_us(duration).await;
+        self.rst.set_high().map_err(EpdError::Pin)?;
+        delay.delay_us(200_000).await;
+        Ok(())
+    }
+
+    /// Runs a command sequence shared with the blocking drivers
+    pub(crate) async fn run<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        busy: Busy<T>,
+        steps: &[Step<'_, T>],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        for step in steps {
+            match *step {
+                Step::Reset(initial_delay, duration) => {
+                    self.reset(delay, initial_delay, duration).await?
+                }
+                Step::Cmd(command) => self.cmd(spi, command).await?,
+                Step::CmdWithData(command, data) => self.cmd_with_data(spi, command, data).await?,
+                Step::DataXTimes(val, repetitions) => {
+                    self.data_x_times(spi, val, repetitions).await?
+                }
+                Step::Delay(us) => delay.delay_us(us).await,
+                Step::WaitUntilIdle => self.wait_until_idle(spi, delay, busy).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use embedded_hal_1::spi::Operation;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingDevice {
+        written: [u8; 8],
+        len: usize,
+    }
+
+    impl embedded_hal_1::spi::ErrorType for RecordingDevice {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for RecordingDevice {
+        async fn transaction(
+            &mut self,
+            operations: &mut [Operation<'_, u8>],
+        ) -> Result<(), Infallible> {
+            for op in operations {
+                if let Operation::Write(words) = op {
+                    self.written[self.len..self.len + words.len()].copy_from_slice(words);
+                    self.len += words.len();
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Pin which reports busy (low) for the first `busy_polls` reads and is idle after that
+    #[derive(Default)]
+    struct TestPin {
+        busy_polls: usize,
+    }
+
+    impl embedded_hal_1::digital::ErrorType for TestPin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for TestPin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    impl InputPin for TestPin {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            let busy = self.busy_polls > 0;
+            self.busy_polls = self.busy_polls.saturating_sub(1);
+            Ok(!busy)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    impl Wait for TestPin {
+        async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        async fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[derive(Clone, Copy)]
+    struct Cmd(u8);
+
+    impl Command for Cmd {
+        fn address(self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn run_sends_steps_in_order() {
+        const STEPS: &[Step<Cmd>] = &[
+            Step::Cmd(Cmd(0x12)),
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Cmd(0x22), &[0xF7]),
+            Step::DataXTimes(0xFF, 2),
+        ];
+        let busy = Busy {
+            is_busy_low: true,
+            status_command: Some(Cmd(0x71)),
+        };
+        let mut spi = RecordingDevice::default();
+        let mut interface =
+            AsyncDisplayInterface::new(TestPin::default(), TestPin::default(), TestPin::default());
+        block_on(interface.run(&mut spi, &mut NoDelay, busy, STEPS)).unwrap();
+        assert_eq!(
+            &spi.written[..spi.len],
+            &[0x12, 0x71, 0x22, 0xF7, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn status_command_is_resent_until_idle() {
+        let busy = Busy {
+            is_busy_low: true,
+            status_command: Some(Cmd(0x71)),
+        };
+        let mut spi = RecordingDevice::default();
+        let mut interface = AsyncDisplayInterface::new(
+            TestPin { busy_polls: 3 },
+            TestPin::default(),
+            TestPin::default(),
+        );
+        block_on(interface.wait_until_idle(&mut spi, &mut NoDelay, busy)).unwrap();
+        assert_eq!(&spi.written[..spi.len], &[0x71; 4]);
+    }
+
+    #[test]
+    fn busy_timeout() {
+        let busy = Busy::<Cmd> {
+            is_busy_low: true,
+            status_command: None,
+        };
+        let mut spi = RecordingDevice::default();
+        let mut interface = AsyncDisplayInterface::new(
+            TestPin { busy_polls: 3 },
+            TestPin::default(),
+            TestPin::default(),
+        );
+        interface.set_busy_timeout(Some(2));
+        assert_eq!(
+            block_on(interface.wait_until_idle(&mut spi, &mut NoDelay, busy)),
+            Err(EpdError::BusyTimeout)
+        );
+
+        let mut interface = AsyncDisplayInterface::new(
+            TestPin { busy_polls: 3 },
+            TestPin::default(),
+            TestPin::default(),
+        );
+        interface.set_busy_timeout(Some(3));
+        block_on(interface.wait_until_idle(&mut spi, &mut NoDelay, busy)).unwrap();
+        assert_eq!(spi.len, 0);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::error::EpdError;
+use crate::traits::RefreshLut;
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+/// Async version of [`WaveshareDisplay`](crate::prelude::WaveshareDisplay)
+///
+/// SPI transfers go through a [`SpiDevice`], which also handles the chip select, and the
+/// BUSY line is awaited with [`Wait`] instead of being polled, so other tasks can run while
+/// the display refreshes. Controllers which only update BUSY after a status command get that
+/// command again every 10ms until they are idle, which is why BUSY also needs [`InputPin`].
+///
+/// The command sequences are the same as in the blocking drivers. Only the Epd 7in5 V2, 2in9 V2
+/// and 2in9 (B/C) implement this trait so far.
+#[allow(async_fn_in_trait)]
+pub trait AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    /// The Color Type used by the Display
+    type DisplayColor;
+
+    /// Creates a new driver from a SPI device, Busy, DC and RST pins
+    ///
+    /// This already initialises the device.
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, EpdError<SPI::Error, BUSY::Error>>
+    where
+        Self: Sized;
+
+    /// Let the device enter deep-sleep mode to save power.
+    async fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Wakes the device up from sleep
+    async fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Sets the backgroundcolor for various commands like [clear_frame](AsyncWaveshareDisplay::clear_frame)
+    fn set_background_color(&mut self, color: Self::DisplayColor);
+
+    /// Get current background color
+    fn background_color(&self) -> &Self::DisplayColor;
+
+    /// Sets how many times the BUSY line is polled, 10ms apart, before giving up with
+    /// [`EpdError::BusyTimeout`]
+    ///
+    /// `None` (the default) awaits the idle level without polling and waits forever.
+    fn set_busy_timeout(&mut self, max_polls: Option<u32>);
+
+    /// Get the width of the display
+    fn width(&self) -> u32;
+
+    /// Get the height of the display
+    fn height(&self) -> u32;
+
+    /// Transmit a full frame to the SRAM of the EPD
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Transmits partial data to the SRAM of the EPD
+    ///
+    /// (x,y) is the top left corner
+    ///
+    /// BUFFER needs to be of size: width / 8 * height !
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Displays the frame data from SRAM
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Provide a combined update&display and save some time (skipping a busy check in between)
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Clears the frame buffer on the EPD with the declared background color
+    async fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Trait for using various Waveforms from different LUTs
+    /// E.g. for partial refreshes
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Waits until the device isn't busy anymore
+    async fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+}
+
+/// Async version of [`WaveshareThreeColorDisplay`](crate::prelude::WaveshareThreeColorDisplay)
+#[allow(async_fn_in_trait)]
+pub trait AsyncWaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>:
+    AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    /// Transmit data to the SRAM of the EPD
+    ///
+    /// Updates both the black and the secondary color layers
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Update only the black/white data of the display.
+    ///
+    /// This must be finished by calling `update_chromatic_frame`.
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Update only the chromatic data of the display.
+    ///
+    /// This should be preceded by a call to `update_achromatic_frame`.
+    /// This data takes precedence over the black/white data.
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Transmit a window of both layers to the SRAM of the EPD
+    ///
+    /// (x,y) is the top left corner, `x` and `width` need to be multiples of 8.
+    /// Both buffers need to be of size: width / 8 * height !
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_partial_achromatic_frame(spi, delay, black, x, y, width, height)
+            .await?;
+        self.update_partial_chromatic_frame(spi, delay, chromatic, x, y, width, height)
+            .await
+    }
+
+    /// Update only the black/white data of a window of the display.
+    ///
+    /// This must be finished by calling `update_partial_chromatic_frame` for the same window.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Update only the chromatic data of a window of the display.
+    ///
+    /// This should be preceded by a call to `update_partial_achromatic_frame`.
+    /// This data takes precedence over the black/white data.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+}
+
+/// Async version of [`QuickRefresh`](crate::prelude::QuickRefresh)
+#[allow(async_fn_in_trait)]
+pub trait AsyncQuickRefresh<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    /// Updates the old frame.
+    async fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Updates the new frame.
+    async fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Displays the new frame
+    async fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Updates and displays the new frame.
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Updates the old frame for a portion of the display.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Updates the new frame for a portion of the display.
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+
+    /// Clears the partial frame buffer on the EPD with the declared background color
+    #[allow(clippy::too_many_arguments)]
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>>;
+}
@@ -0,0 +1,277 @@
+//! Band rendering: drawing frames without a full size buffer
+//!
+//! A [`BandRenderer`] splits the frame into horizontal bands of as many rows as fit into a small
+//! scratch buffer. For each band it calls your drawing code with a [`Band`], which is an
+//! embedded-graphics `DrawTarget` covering the whole display but only keeping the pixels of the
+//! current band, and streams the result into the controller RAM. This way any display can be
+//! driven from a few kilobytes of RAM, at the cost of running the drawing code once per band.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use embedded_graphics::{
+//!    prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{band::BandRenderer, epd7in5_hd::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!let mut epd = Epd7in5::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
+//!
+//!// 16 rows of 880 pixels
+//!let mut scratch = [0; 880 / 8 * 16];
+//!let mut renderer = BandRenderer::new(&mut scratch, false);
+//!
+//!renderer.update_frame(&mut epd, &mut spi, &mut delay, |band| {
+//!    let _ = band.clear(Color::White);
+//!    let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+//!        .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
+//!        .draw(band);
+//!})?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::color::ColorType;
+use crate::error::EpdError;
+use crate::graphics::{line_bytes, rotate, set_pixel, DisplayRotation};
+use crate::traits::WaveshareStreamingDisplay;
+use core::marker::PhantomData;
+use embedded_graphics_core::prelude::*;
+use embedded_hal::{
+    blocking::{delay::DelayUs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+/// Renders frames band by band into a scratch buffer and streams them to the display
+pub struct BandRenderer<'a> {
+    buffer: &'a mut [u8],
+    rotation: DisplayRotation,
+    bwrbit: bool,
+}
+
+impl<'a> BandRenderer<'a> {
+    /// Creates a renderer using `buffer` for the bands
+    ///
+    /// The buffer needs to hold at least one row of the display. `bwrbit` is the same as for
+    /// [`Display`](crate::graphics::Display) and should be false for non tricolor displays.
+    pub fn new(buffer: &'a mut [u8], bwrbit: bool) -> Self {
+        BandRenderer {
+            buffer,
+            rotation: DisplayRotation::default(),
+            bwrbit,
+        }
+    }
+
+    /// Set the rotation used for drawing
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get current rotation
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// Draws a full frame with `draw`, called once per band, and transmits it to the SRAM of
+    /// the EPD
+    ///
+    /// This replaces [`update_frame`](crate::prelude::WaveshareDisplay::update_frame), the frame
+    /// still needs to be displayed afterwards. Every band starts out with all bits cleared, like
+    /// a new [`Display`](crate::graphics::Display).
+    pub fn update_frame<SPI, CS, BUSY, DC, RST, DELAY, EPD, F>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mut draw: F,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+        EPD: WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        EPD::DisplayColor: ColorType + PixelColor,
+        F: FnMut(&mut Band<'_, EPD::DisplayColor>),
+    {
+        let planes = EPD::DisplayColor::BUFFER_COUNT;
+        let width = epd.width();
+        let height = epd.height();
+        let row_len = line_bytes(width, EPD::DisplayColor::BITS_PER_PIXEL_PER_BUFFER) * planes;
+        let rows = (self.buffer.len() / row_len) as u32;
+        if rows == 0 {
+            return Err(EpdError::BufferSize {
+                expected: row_len,
+                actual: self.buffer.len(),
+            });
+        }
+
+        for pass in 0..epd.frame_passes() {
+            epd.begin_frame_pass(spi, delay, pass)?;
+            let plane = pass % planes;
+
+            let mut top = 0;
+            while top < height {
+                let band_rows = rows.min(height - top);
+                let buffer = &mut self.buffer[..band_rows as usize * row_len];
+                buffer.fill(0);
+
+                draw(&mut Band {
+                    buffer,
+                    width,
+                    height,
+                    top,
+                    rows: band_rows,
+                    rotation: self.rotation,
+                    bwrbit: self.bwrbit,
+                    _color: PhantomData,
+                });
+
+                let buffer = &self.buffer[..band_rows as usize * row_len];
+                let plane_len = buffer.len() / planes;
+                epd.write_frame_rows(spi, &buffer[plane * plane_len..][..plane_len])?;
+                top += band_rows;
+            }
+        }
+        epd.end_frame(spi, delay)
+    }
+}
+
+/// A band of rows of the frame, drawn by the closure passed to [`BandRenderer::update_frame`]
+///
+/// It has the size of the whole display, pixels outside the current band are skipped.
+pub struct Band<'a, COLOR: ColorType> {
+    buffer: &'a mut [u8],
+    width: u32,
+    height: u32,
+    top: u32,
+    rows: u32,
+    rotation: DisplayRotation,
+    bwrbit: bool,
+    _color: PhantomData<COLOR>,
+}
+
+impl<'a, COLOR: ColorType> Band<'a, COLOR> {
+    /// First row of the band, in unrotated display coordinates
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+
+    /// Number of rows in the band
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Set a specific pixel color, if it is part of this band
+    pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
+        let Pixel(point, color) = pixel;
+        let (x, y) = rotate(point, self.width, self.height, self.rotation);
+        let y = y - self.top as i32;
+        if x < 0 || x >= self.width as i32 || y < 0 || y >= self.rows as i32 {
+            return;
+        }
+        self.set_band_pixel(Point::new(x, y), color);
+    }
+
+    fn set_band_pixel(&mut self, point: Point, color: COLOR) {
+        set_pixel(
+            self.buffer,
+            self.width,
+            self.rows,
+            DisplayRotation::Rotate0,
+            self.bwrbit,
+            Pixel(point, color),
+        );
+    }
+}
+
+impl<'a, COLOR: ColorType + PixelColor> DrawTarget for Band<'a, COLOR> {
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.set_pixel(pixel);
+        }
+        Ok(())
+    }
+
+    // only fill the rows of this band instead of the whole display
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        for y in 0..self.rows as i32 {
+            for x in 0..self.width as i32 {
+                self.set_band_pixel(Point::new(x, y), color);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, COLOR: ColorType> OriginDimensions for Band<'a, COLOR> {
+    fn size(&self) -> Size {
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(self.width, self.height)
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(self.height, self.width)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn band(buffer: &mut [u8], top: u32, rotation: DisplayRotation) -> Band<'_, Color> {
+        Band {
+            buffer,
+            width: 16,
+            height: 32,
+            top,
+            rows: 2,
+            rotation,
+            bwrbit: false,
+            _color: PhantomData,
+        }
+    }
+
+    #[test]
+    fn band_keeps_its_rows_only() {
+        let mut buffer = [0; 4];
+        let mut band = band(&mut buffer, 8, DisplayRotation::Rotate0);
+        band.set_pixel(Pixel(Point::new(0, 7), Color::White));
+        band.set_pixel(Pixel(Point::new(9, 9), Color::White));
+        band.set_pixel(Pixel(Point::new(0, 10), Color::White));
+        assert_eq!(band.size(), Size::new(16, 32));
+        assert_eq!(buffer, [0, 0, 0, 0x40]);
+    }
+
+    #[test]
+    fn band_rotation() {
+        let mut buffer = [0; 4];
+        let mut band = band(&mut buffer, 8, DisplayRotation::Rotate90);
+        // (8, 15) rotated by 90° is (0, 8), the first pixel of the band
+        band.set_pixel(Pixel(Point::new(8, 15), Color::White));
+        assert_eq!(band.size(), Size::new(32, 16));
+        assert_eq!(buffer, [0x80, 0, 0, 0]);
+    }
+}
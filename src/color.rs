@@ -8,6 +8,14 @@ use embedded_graphics_core::pixelcolor::BinaryColor;
 #[cfg(feature = "graphics")]
 use embedded_graphics_core::pixelcolor::PixelColor;
 
+/// 4-level gray color for displays in grayscale mode, from embedded-graphics
+///
+/// In a [`Display`](crate::graphics::Display) the high bit of the luma is kept in the first
+/// half of the buffer and the low bit in the second half, which is the old/new RAM layout the
+/// controllers expect in 4-gray mode.
+#[cfg(feature = "graphics")]
+pub use embedded_graphics_core::pixelcolor::Gray2;
+
 /// When trying to parse u8 to one of the color types
 #[derive(Debug, PartialEq, Eq)]
 pub struct OutOfColorRangeParseError(u8);
@@ -78,14 +86,21 @@ pub trait ColorType: PixelColor {
     /// Return the data used to set a pixel color
     ///
     /// * bwrbit is used to tell the value of the unused bit when a chromatic
-    /// color is set (TriColor only as for now)
+    ///   color is set (TriColor only as for now)
     /// * pos is the pixel position in the line, used to know which pixels must be set
     ///
     /// Return values are :
     /// * .0 is the mask used to exclude this pixel from the byte (eg: 0x7F in BiColor)
     /// * .1 are the bits used to set the color in the byte (eg: 0x80 in BiColor)
-    ///      this is u16 because we set 2 bytes in case of split buffer
+    ///   this is u16 because we set 2 bytes in case of split buffer
     fn bitmask(&self, bwrbit: bool, pos: u32) -> (u8, u16);
+
+    /// Return the color of a pixel from the data containing it, the inverse of `bitmask`
+    ///
+    /// * bits is the byte containing the pixel, with the byte of the second buffer in the
+    ///   upper 8 bits in case of split buffer
+    /// * pos is the pixel position in the line
+    fn from_bits(bits: u16, pos: u32) -> Self;
 }
 
 impl ColorType for Color {
@@ -98,6 +113,14 @@ impl ColorType for Color {
             Color::White => (!bit, bit as u16),
         }
     }
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let bit = 0x80 >> (pos % 8);
+        if bits & bit != 0 {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
 }
 
 impl ColorType for TriColor {
@@ -118,16 +141,58 @@ impl ColorType for TriColor {
             ),
         }
     }
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        // the chromatic bit wins whatever the value of the b/w bit (BWRBIT)
+        let bit = 0x80 >> (pos % 8);
+        if (bits >> 8) & bit != 0 {
+            TriColor::Chromatic
+        } else if bits & bit != 0 {
+            TriColor::White
+        } else {
+            TriColor::Black
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl ColorType for Gray2 {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 1;
+    const BUFFER_COUNT: usize = 2;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        use embedded_graphics_core::pixelcolor::GrayColor;
+        // the high bit of the luma goes into the first buffer, the low bit into the second
+        let bit = 0x80 >> (pos % 8);
+        let luma = self.luma();
+        let mut bits = 0u16;
+        if luma & 0b10 != 0 {
+            bits |= bit as u16;
+        }
+        if luma & 0b01 != 0 {
+            bits |= (bit as u16) << 8;
+        }
+        (!bit, bits)
+    }
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let bit = 0x80 >> (pos % 8);
+        let high = (bits & bit != 0) as u8;
+        let low = ((bits >> 8) & bit != 0) as u8;
+        Gray2::new(high << 1 | low)
+    }
 }
 
 impl ColorType for OctColor {
     const BITS_PER_PIXEL_PER_BUFFER: usize = 4;
     const BUFFER_COUNT: usize = 1;
     fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
-        let mask = !(0xF0 >> (pos % 2));
+        let mask = !(0xF0 >> (pos % 2 * 4));
         let bits = self.get_nibble() as u16;
         (mask, if pos % 2 == 1 { bits } else { bits << 4 })
     }
+    fn from_bits(bits: u16, pos: u32) -> Self {
+        let nibble = if pos % 2 == 1 { bits } else { bits >> 4 };
+        // only the clean color is left for undefined nibbles
+        OctColor::from_nibble(nibble as u8).unwrap_or(OctColor::HiZ)
+    }
 }
 
 #[cfg(feature = "graphics")]
@@ -244,7 +309,7 @@ impl OctColor {
 
 impl Color {
     /// Get the color encoding of the color for one bit
-    pub fn get_bit_value(self) -> u8 {
+    pub const fn get_bit_value(self) -> u8 {
         match self {
             Color::White => 1u8,
             Color::Black => 0u8,
@@ -252,7 +317,7 @@ impl Color {
     }
 
     /// Gets a full byte of black or white pixels
-    pub fn get_byte_value(self) -> u8 {
+    pub const fn get_byte_value(self) -> u8 {
         match self {
             Color::White => 0xff,
             Color::Black => 0x00,
@@ -335,7 +400,7 @@ impl From<Color> for embedded_graphics_core::pixelcolor::Rgb888 {
 
 impl TriColor {
     /// Get the color encoding of the color for one bit
-    pub fn get_bit_value(self) -> u8 {
+    pub const fn get_bit_value(self) -> u8 {
         match self {
             TriColor::White => 1u8,
             TriColor::Black | TriColor::Chromatic => 0u8,
@@ -343,7 +408,7 @@ impl TriColor {
     }
 
     /// Gets a full byte of black or white pixels
-    pub fn get_byte_value(self) -> u8 {
+    pub const fn get_byte_value(self) -> u8 {
         match self {
             TriColor::White => 0xff,
             TriColor::Black | TriColor::Chromatic => 0x00,
@@ -405,7 +470,7 @@ mod tests {
     // test all values aside from 0 and 1 which all should panic
     #[test]
     fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
+        for val in 2..=u8::MAX {
             extern crate std;
             let result = std::panic::catch_unwind(|| Color::from(val));
             assert!(result.is_err());
@@ -433,4 +498,30 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn oct_bitmask_keeps_the_other_pixel() {
+        let byte = OctColor::colors_byte(OctColor::Red, OctColor::Green);
+        for (pos, expected) in [
+            (4, (OctColor::Blue, OctColor::Green)),
+            (5, (OctColor::Red, OctColor::Blue)),
+        ] {
+            let (mask, bits) = OctColor::Blue.bitmask(false, pos);
+            let drawn = byte & mask | bits as u8;
+            assert_eq!(OctColor::split_byte(drawn), Ok(expected));
+            assert_eq!(OctColor::from_bits(drawn as u16, pos), OctColor::Blue);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn gray2_planes() {
+        for luma in 0..4 {
+            let (mask, bits) = Gray2::new(luma).bitmask(false, 9);
+            assert_eq!(mask, !0x40);
+            assert_eq!(bits & 0xFF != 0, luma & 0b10 != 0);
+            assert_eq!(bits >> 8 != 0, luma & 0b01 != 0);
+            assert_eq!(Gray2::from_bits(bits, 9), Gray2::new(luma));
+        }
+    }
 }
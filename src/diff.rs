@@ -0,0 +1,664 @@
+//! Finding the parts of a frame that changed
+//!
+//! When a whole new frame is rendered, [`FrameDiff`] compares it with the previous one and finds
+//! the changed regions, so only those need to be sent to the display. Both frames use the layout
+//! of the [`Display`](crate::graphics::Display) buffers, including the second plane of colors
+//! like [`TriColor`].
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use epd_waveshare::{diff::{DiffUpdate, FrameDiff}, epd2in9::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!# let old = Display2in9::default();
+//!# let new = Display2in9::default();
+//!
+//!let mut epd = Epd2in9::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
+//!epd.set_refresh_mode(&mut spi, &mut delay, RefreshMode::Partial)?;
+//!
+//!let diff = FrameDiff::<Color>::new(WIDTH, HEIGHT);
+//!let mut scratch = [0u8; WIDTH as usize / 8 * HEIGHT as usize];
+//!let update = diff.update_partial_frame(
+//!    &mut epd, &mut spi, &mut delay, old.buffer(), new.buffer(), &mut scratch,
+//!)?;
+//!if update != DiffUpdate::Unchanged {
+//!    epd.display_frame(&mut spi, &mut delay)?;
+//!}
+//!# Ok(())
+//!# }
+//!```
+
+use core::marker::PhantomData;
+
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::{ColorType, TriColor};
+use crate::error::{check_buffer_len, EpdError};
+use crate::graphics::{copy_window, line_bytes};
+use crate::traits::{QuickRefresh, WaveshareDisplay, WaveshareThreeColorDisplay};
+
+/// What [`FrameDiff`] sent to the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffUpdate {
+    /// Both frames are the same, nothing was sent
+    Unchanged,
+    /// The given number of changed regions was sent as partial updates
+    Partial(usize),
+    /// So much changed that the whole frame was sent
+    Full,
+}
+
+/// Compares frames of a display with the given geometry and color type
+#[derive(Debug, Clone, Copy)]
+pub struct FrameDiff<COLOR> {
+    width: u32,
+    height: u32,
+    merge_gap: u32,
+    full_update_percent: u8,
+    _color: PhantomData<COLOR>,
+}
+
+impl<COLOR: ColorType> FrameDiff<COLOR> {
+    /// Compares frames of `width` x `height` pixels, with the width and height of the unrotated
+    /// display
+    pub fn new(width: u32, height: u32) -> Self {
+        FrameDiff {
+            width,
+            height,
+            merge_gap: 8,
+            full_update_percent: 50,
+            _color: PhantomData,
+        }
+    }
+
+    /// Sets how many unchanged rows may lie between two changed ones for them to still be sent
+    /// as one region, 8 by default
+    ///
+    /// Every region is a separate transfer, so merging close regions saves overhead.
+    pub fn set_merge_gap(&mut self, rows: u32) {
+        self.merge_gap = rows;
+    }
+
+    /// Sets how much of the frame, in percent, has to change for the whole frame to be sent
+    /// instead of the changed regions, 50 by default
+    pub fn set_full_update_threshold(&mut self, percent: u8) {
+        self.full_update_percent = percent;
+    }
+
+    /// Number of bytes of a frame
+    pub fn frame_len(&self) -> usize {
+        self.line_len() * self.height as usize * COLOR::BUFFER_COUNT
+    }
+
+    fn line_len(&self) -> usize {
+        line_bytes(self.width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+    }
+
+    /// The regions in which `old` and `new` differ, from top to bottom
+    ///
+    /// The left and right edges of every region are aligned to bytes of 8 pixels, except for a
+    /// right edge at the edge of the display. If one of the frames doesn't have
+    /// [frame_len](FrameDiff::frame_len) bytes, the whole frame is reported as changed.
+    pub fn changed_regions<'a>(&self, old: &'a [u8], new: &'a [u8]) -> Regions<'a, COLOR> {
+        Regions {
+            diff: *self,
+            old,
+            new,
+            row: 0,
+            whole_frame: old.len() != self.frame_len() || new.len() != self.frame_len(),
+        }
+    }
+
+    // Whether to send the whole frame, or None if nothing changed
+    fn needs_full_update(&self, old: &[u8], new: &[u8]) -> Option<bool> {
+        let mut changed = 0;
+        let mut any = false;
+        for region in self.changed_regions(old, new) {
+            any = true;
+            changed += region.size.width as usize * region.size.height as usize;
+        }
+        let total = self.width as usize * self.height as usize;
+        any.then_some(changed * 100 >= total * self.full_update_percent as usize)
+    }
+
+    /// Sends the changed regions of `new` with
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame), or all of it with
+    /// [update_frame](WaveshareDisplay::update_frame) if much has changed
+    ///
+    /// The regions are copied into `scratch` first, which needs room for the largest one; a
+    /// buffer of [frame_len](FrameDiff::frame_len) bytes always works. Call
+    /// [display_frame](WaveshareDisplay::display_frame) afterwards to show the frame.
+    ///
+    /// Both methods take a single plane, so for colors with two planes only the first one is
+    /// sent: tricolor displays clear their chromatic plane. Use
+    /// [update_partial_color_frame](FrameDiff::update_partial_color_frame) to send both.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_frame<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        old: &[u8],
+        new: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<DiffUpdate, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        check_buffer_len(old, self.frame_len())?;
+        check_buffer_len(new, self.frame_len())?;
+        match self.needs_full_update(old, new) {
+            None => Ok(DiffUpdate::Unchanged),
+            Some(true) => {
+                epd.update_frame(spi, first_plane::<COLOR>(new), delay)?;
+                Ok(DiffUpdate::Full)
+            }
+            Some(false) => {
+                let mut count = 0;
+                for region in self.changed_regions(old, new) {
+                    let len = self.copy_region(new, &region, scratch)?;
+                    epd.update_partial_frame(
+                        spi,
+                        delay,
+                        first_plane::<COLOR>(&scratch[..len]),
+                        region.top_left.x as u32,
+                        region.top_left.y as u32,
+                        region.size.width,
+                        region.size.height,
+                    )?;
+                    count += 1;
+                }
+                Ok(DiffUpdate::Partial(count))
+            }
+        }
+    }
+
+    /// Sends the changed regions of both frames with
+    /// [update_partial_old_frame](QuickRefresh::update_partial_old_frame) and
+    /// [update_partial_new_frame](QuickRefresh::update_partial_new_frame), or the whole frames
+    /// with [update_old_frame](QuickRefresh::update_old_frame) and
+    /// [update_new_frame](QuickRefresh::update_new_frame) if much has changed
+    ///
+    /// `scratch` is used like in [update_partial_frame](FrameDiff::update_partial_frame), and
+    /// only the first plane of colors with two planes is sent as well. Call
+    /// [display_new_frame](QuickRefresh::display_new_frame) afterwards to show the frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_quick_frames<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        old: &[u8],
+        new: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<DiffUpdate, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        check_buffer_len(old, self.frame_len())?;
+        check_buffer_len(new, self.frame_len())?;
+        match self.needs_full_update(old, new) {
+            None => Ok(DiffUpdate::Unchanged),
+            Some(true) => {
+                epd.update_old_frame(spi, first_plane::<COLOR>(old), delay)?;
+                epd.update_new_frame(spi, first_plane::<COLOR>(new), delay)?;
+                Ok(DiffUpdate::Full)
+            }
+            Some(false) => {
+                let mut count = 0;
+                for region in self.changed_regions(old, new) {
+                    let (x, y) = (region.top_left.x as u32, region.top_left.y as u32);
+                    let (width, height) = (region.size.width, region.size.height);
+                    let len = self.copy_region(old, &region, scratch)?;
+                    let window = first_plane::<COLOR>(&scratch[..len]);
+                    epd.update_partial_old_frame(spi, delay, window, x, y, width, height)?;
+                    let len = self.copy_region(new, &region, scratch)?;
+                    let window = first_plane::<COLOR>(&scratch[..len]);
+                    epd.update_partial_new_frame(spi, delay, window, x, y, width, height)?;
+                    count += 1;
+                }
+                Ok(DiffUpdate::Partial(count))
+            }
+        }
+    }
+
+    fn copy_region<SpiE, PinE>(
+        &self,
+        frame: &[u8],
+        region: &Rectangle,
+        scratch: &mut [u8],
+    ) -> Result<usize, EpdError<SpiE, PinE>> {
+        copy_window::<COLOR>(frame, self.width, region, scratch).ok_or(EpdError::BufferSize {
+            expected: line_bytes(region.size.width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+                * region.size.height as usize
+                * COLOR::BUFFER_COUNT,
+            actual: scratch.len(),
+        })
+    }
+}
+
+impl FrameDiff<TriColor> {
+    /// Sends both planes of the changed regions of `new` with
+    /// [update_partial_color_frame](WaveshareThreeColorDisplay::update_partial_color_frame), or
+    /// all of it with [update_color_frame](WaveshareThreeColorDisplay::update_color_frame) if
+    /// much has changed
+    ///
+    /// `scratch` is used like in [update_partial_frame](FrameDiff::update_partial_frame). Call
+    /// [display_frame](WaveshareDisplay::display_frame) afterwards to show the frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_partial_color_frame<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        old: &[u8],
+        new: &[u8],
+        scratch: &mut [u8],
+    ) -> Result<DiffUpdate, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        check_buffer_len(old, self.frame_len())?;
+        check_buffer_len(new, self.frame_len())?;
+        match self.needs_full_update(old, new) {
+            None => Ok(DiffUpdate::Unchanged),
+            Some(true) => {
+                let (black, chromatic) = new.split_at(new.len() / 2);
+                epd.update_color_frame(spi, delay, black, chromatic)?;
+                Ok(DiffUpdate::Full)
+            }
+            Some(false) => {
+                let mut count = 0;
+                for region in self.changed_regions(old, new) {
+                    let len = self.copy_region(new, &region, scratch)?;
+                    let (black, chromatic) = scratch[..len].split_at(len / 2);
+                    epd.update_partial_color_frame(
+                        spi,
+                        delay,
+                        black,
+                        chromatic,
+                        region.top_left.x as u32,
+                        region.top_left.y as u32,
+                        region.size.width,
+                        region.size.height,
+                    )?;
+                    count += 1;
+                }
+                Ok(DiffUpdate::Partial(count))
+            }
+        }
+    }
+}
+
+// The first plane of a frame or window laid out like the buffers of `Display`
+fn first_plane<COLOR: ColorType>(planes: &[u8]) -> &[u8] {
+    &planes[..planes.len() / COLOR::BUFFER_COUNT]
+}
+
+/// Iterator over the changed regions of two frames, see [`FrameDiff::changed_regions`]
+pub struct Regions<'a, COLOR> {
+    diff: FrameDiff<COLOR>,
+    old: &'a [u8],
+    new: &'a [u8],
+    row: u32,
+    whole_frame: bool,
+}
+
+impl<'a, COLOR: ColorType> Regions<'a, COLOR> {
+    // First and last changed byte of a row, over all planes
+    fn changed_bytes(&self, row: u32) -> Option<(usize, usize)> {
+        let line = self.diff.line_len();
+        let plane_len = line * self.diff.height as usize;
+        let mut changed: Option<(usize, usize)> = None;
+        for plane in 0..COLOR::BUFFER_COUNT {
+            let start = plane * plane_len + row as usize * line;
+            let old = &self.old[start..start + line];
+            let new = &self.new[start..start + line];
+            let first = old.iter().zip(new).position(|(o, n)| o != n);
+            let last = old.iter().zip(new).rposition(|(o, n)| o != n);
+            if let (Some(first), Some(last)) = (first, last) {
+                changed = Some(match changed {
+                    None => (first, last),
+                    Some((a, b)) => (a.min(first), b.max(last)),
+                });
+            }
+        }
+        changed
+    }
+}
+
+impl<'a, COLOR: ColorType> Iterator for Regions<'a, COLOR> {
+    type Item = Rectangle;
+
+    fn next(&mut self) -> Option<Rectangle> {
+        let (width, height) = (self.diff.width, self.diff.height);
+        if self.whole_frame {
+            if self.row != 0 {
+                return None;
+            }
+            self.row = height;
+            return Some(Rectangle::new(Point::zero(), Size::new(width, height)));
+        }
+
+        // top, bottom, first byte, last byte
+        let mut band: Option<(u32, u32, usize, usize)> = None;
+        let mut gap = 0;
+        while self.row < height {
+            match (self.changed_bytes(self.row), band) {
+                (Some((first, last)), None) => band = Some((self.row, self.row, first, last)),
+                (Some((first, last)), Some((top, _, a, b))) => {
+                    band = Some((top, self.row, a.min(first), b.max(last)));
+                    gap = 0;
+                }
+                (None, Some(_)) => {
+                    gap += 1;
+                    if gap > self.diff.merge_gap {
+                        break;
+                    }
+                }
+                (None, None) => {}
+            }
+            self.row += 1;
+        }
+
+        band.map(|(top, bottom, first, last)| {
+            let bits = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+            let left = (first * 8 / bits) as u32 / 8 * 8;
+            let right = ((last + 1) * 8 / bits) as u32;
+            // without going past the right edge of the display
+            let right = (right.div_ceil(8) * 8).min(width);
+            Rectangle::new(
+                Point::new(left as i32, top as i32),
+                Size::new(right - left, bottom - top + 1),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Color, OctColor, TriColor};
+    use crate::graphics::VarDisplay;
+
+    #[test]
+    fn regions_are_merged_and_aligned() {
+        let old = [0u8; 4 * 40];
+        let mut new = old;
+        // row 2, pixels 8..16 and 24..32
+        new[2 * 4 + 1] = 0xFF;
+        new[2 * 4 + 3] = 0x01;
+        // row 5 is close enough to be merged, row 30 isn't
+        new[5 * 4] = 0x80;
+        new[30 * 4 + 2] = 0x10;
+
+        let mut diff = FrameDiff::<Color>::new(32, 40);
+        diff.set_merge_gap(4);
+        let mut regions = diff.changed_regions(&old, &new);
+        assert_eq!(
+            regions.next(),
+            Some(Rectangle::new(Point::new(0, 2), Size::new(32, 4)))
+        );
+        assert_eq!(
+            regions.next(),
+            Some(Rectangle::new(Point::new(16, 30), Size::new(8, 1)))
+        );
+        assert_eq!(regions.next(), None);
+        assert_eq!(diff.changed_regions(&old, &old).next(), None);
+        assert_eq!(
+            diff.changed_regions(&old, &new[1..]).next(),
+            Some(Rectangle::new(Point::zero(), Size::new(32, 40)))
+        );
+    }
+
+    #[test]
+    fn chromatic_plane_changes_count() {
+        let mut old_buffer = [0u8; 2 * 16 / 8 * 4];
+        let mut new_buffer = old_buffer;
+        let old = VarDisplay::<TriColor>::new(16, 4, &mut old_buffer, true).unwrap();
+        let mut new = VarDisplay::<TriColor>::new(16, 4, &mut new_buffer, true).unwrap();
+        new.set_pixel(Pixel(Point::new(12, 3), TriColor::Chromatic));
+
+        let diff = FrameDiff::<TriColor>::new(16, 4);
+        let regions = diff.changed_regions(old.buffer(), new.buffer());
+        assert!(regions.eq([Rectangle::new(Point::new(8, 3), Size::new(8, 1))]));
+    }
+
+    #[test]
+    fn four_bit_pixels_are_aligned() {
+        let old = [0x11u8; 24 / 2 * 2];
+        let mut new = old;
+        // second row, pixels 10 and 11
+        new[12 + 5] = 0x22;
+
+        let diff = FrameDiff::<OctColor>::new(24, 2);
+        let regions = diff.changed_regions(&old, &new);
+        assert!(regions.eq([Rectangle::new(Point::new(8, 1), Size::new(8, 1))]));
+    }
+
+    #[test]
+    fn regions_stop_at_the_right_edge() {
+        // 122 pixels leave 2 pixels in the last byte of a line
+        let old = [0u8; 16 * 3];
+        let mut new = old;
+        new[16 + 15] = 0x40;
+
+        let diff = FrameDiff::<Color>::new(122, 3);
+        let regions = diff.changed_regions(&old, &new);
+        assert!(regions.eq([Rectangle::new(Point::new(120, 1), Size::new(2, 1))]));
+        assert!(diff
+            .changed_regions(&old, &new[1..])
+            .eq([Rectangle::new(Point::zero(), Size::new(122, 3))]));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_and_full_updates() {
+        use crate::epd2in13_v2::{Epd2in13, HEIGHT, WIDTH};
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use std::vec;
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in13::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let diff = FrameDiff::<Color>::new(WIDTH, HEIGHT);
+        let mut scratch = vec![0u8; diff.frame_len()];
+        let old = vec![0u8; diff.frame_len()];
+        let mut new = old.clone();
+        // the last two pixels of row 10
+        new[10 * 16 + 15] = 0xC0;
+
+        let update =
+            diff.update_partial_frame(&mut epd, &mut spi, &mut SimDelay, &old, &new, &mut scratch);
+        assert_eq!(update, Ok(DiffUpdate::Partial(1)));
+        assert_eq!(sim.ram(0), new);
+
+        let newer = vec![0xAAu8; diff.frame_len()];
+        let update = diff.update_partial_frame(
+            &mut epd,
+            &mut spi,
+            &mut SimDelay,
+            &new,
+            &newer,
+            &mut scratch,
+        );
+        assert_eq!(update, Ok(DiffUpdate::Full));
+        assert_eq!(sim.ram(0), newer);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn tricolor_updates() {
+        use crate::epd2in13bc::{Epd2in13bc, HEIGHT, WIDTH};
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use std::vec;
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in13bc::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let diff = FrameDiff::<TriColor>::new(WIDTH, HEIGHT);
+        let plane_len = diff.frame_len() / 2;
+        let mut scratch = vec![0u8; diff.frame_len()];
+        let old = vec![0u8; diff.frame_len()];
+        let mut new = old.clone();
+        // byte 1 of row 5 in both planes
+        let index = 5 * 13 + 1;
+        new[index] = 0x0F;
+        new[plane_len + index] = 0x20;
+
+        let update = diff.update_partial_color_frame(
+            &mut epd,
+            &mut spi,
+            &mut SimDelay,
+            &old,
+            &new,
+            &mut scratch,
+        );
+        assert_eq!(update, Ok(DiffUpdate::Partial(1)));
+        assert_eq!(sim.ram(0)[index], 0x0F);
+        assert_eq!(sim.ram(1)[index], 0x20);
+
+        // only the black plane, the chromatic one is cleared
+        let update =
+            diff.update_partial_frame(&mut epd, &mut spi, &mut SimDelay, &old, &new, &mut scratch);
+        assert_eq!(update, Ok(DiffUpdate::Partial(1)));
+        assert_eq!(sim.ram(0)[index], 0x0F);
+        assert_eq!(sim.ram(1)[index], 0xFF);
+
+        let mut newer = vec![0x55u8; diff.frame_len()];
+        newer[plane_len..].fill(0x33);
+        let update = diff.update_partial_color_frame(
+            &mut epd,
+            &mut spi,
+            &mut SimDelay,
+            &old,
+            &newer,
+            &mut scratch,
+        );
+        assert_eq!(update, Ok(DiffUpdate::Full));
+        assert_eq!(sim.ram(0), newer[..plane_len]);
+        assert_eq!(sim.ram(1), newer[plane_len..]);
+
+        let update = diff.update_partial_frame(
+            &mut epd,
+            &mut spi,
+            &mut SimDelay,
+            &old,
+            &newer,
+            &mut scratch,
+        );
+        assert_eq!(update, Ok(DiffUpdate::Full));
+        assert_eq!(sim.ram(0), newer[..plane_len]);
+        assert!(sim.ram(1).iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn quick_frames() {
+        use crate::epd2in9_v2::{Epd2in9, HEIGHT, WIDTH};
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use std::vec;
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+
+        let diff = FrameDiff::<Color>::new(WIDTH, HEIGHT);
+        let mut scratch = vec![0u8; diff.frame_len()];
+        let old = vec![0u8; diff.frame_len()];
+        let mut new = old.clone();
+        new[7 * 16 + 3] = 0x81;
+        let update =
+            diff.update_quick_frames(&mut epd, &mut spi, &mut SimDelay, &old, &new, &mut scratch);
+        assert_eq!(update, Ok(DiffUpdate::Partial(1)));
+        assert_eq!(sim.ram(0), new);
+        assert_eq!(sim.ram(1), old);
+
+        // the black planes of tricolor frames
+        let diff = FrameDiff::<TriColor>::new(WIDTH, HEIGHT);
+        let plane_len = diff.frame_len() / 2;
+        let mut old = vec![0u8; diff.frame_len()];
+        old[..plane_len].fill(0x11);
+        let mut new = old.clone();
+        new[9 * 16 + 4] = 0x3C;
+        new[plane_len + 9 * 16 + 4] = 0xFF;
+        let mut scratch = vec![0u8; diff.frame_len()];
+        let update =
+            diff.update_quick_frames(&mut epd, &mut spi, &mut SimDelay, &old, &new, &mut scratch);
+        assert_eq!(update, Ok(DiffUpdate::Partial(1)));
+        assert_eq!(sim.ram(0)[9 * 16 + 4], 0x3C);
+        assert_eq!(sim.ram(1)[9 * 16 + 4], 0x11);
+
+        let newer = vec![0x77u8; diff.frame_len()];
+        let update = diff.update_quick_frames(
+            &mut epd,
+            &mut spi,
+            &mut SimDelay,
+            &old,
+            &newer,
+            &mut scratch,
+        );
+        assert_eq!(update, Ok(DiffUpdate::Full));
+        assert_eq!(sim.ram(0), newer[..plane_len]);
+        assert_eq!(sim.ram(1), old[..plane_len]);
+    }
+}
@@ -0,0 +1,442 @@
+//! Dithering of RGB and gray images
+//!
+//! A [`Ditherer`] quantizes an image to the colors of a [`palette`](Ditherer::new) and draws the
+//! result into any embedded-graphics `DrawTarget`, e.g. a
+//! [`Display`](crate::graphics::Display), a [`VarDisplay`](crate::graphics::VarDisplay) or a
+//! [`Band`](crate::band::Band). The quantization error of every pixel is spread to its
+//! neighbours with one of the [`Kernel`]s, which gives far better photos than picking the
+//! nearest color of each pixel.
+//!
+//! Each palette entry maps a display color to the RGB value it looks like on the panel. The
+//! default palettes use the same idealized values as the `Rgb888` conversions, measured pigment
+//! colors can be used instead for a more faithful result.
+//!
+//! For drawing embedded-graphics widgets, images and anti-aliased fonts directly,
+//! [`OrderedDither`] wraps a display into a `DrawTarget` accepting `Rgb888` or `Gray8` and
+//! applies Bayer ordered dithering, which needs no memory at all.
+//!
+//! # Example
+//!
+//!```rust
+//!use embedded_graphics::{pixelcolor::Rgb888, prelude::*};
+//!use epd_waveshare::{
+//!    dither::{self, Ditherer, Kernel},
+//!    epd5in65f::Display5in65f,
+//!    prelude::*,
+//!};
+//!
+//!let mut display = Display5in65f::default();
+//!
+//!// three rows of errors for an image of 600 pixels per line
+//!let mut errors = [0; Ditherer::<OctColor>::errors_len(600)];
+//!let mut ditherer = Ditherer::new(Kernel::FloydSteinberg, &dither::SEVEN_COLOR, &mut errors);
+//!
+//!let gradient = (0..448).flat_map(|y| (0..600).map(move |x| Rgb888::new(x as u8, y as u8, 128)));
+//!ditherer.draw(&mut display, Point::zero(), 600, gradient).unwrap();
+//!```
+
+use crate::color::{Color, OctColor, TriColor};
+use core::marker::PhantomData;
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+
+/// Black and white palette for [`Color`] displays
+pub const BLACK_WHITE: [(Color, Rgb888); 2] =
+    [(Color::Black, Rgb888::BLACK), (Color::White, Rgb888::WHITE)];
+
+/// Black, white and red palette for [`TriColor`] displays
+pub const BLACK_WHITE_RED: [(TriColor, Rgb888); 3] = [
+    (TriColor::Black, Rgb888::BLACK),
+    (TriColor::White, Rgb888::WHITE),
+    (TriColor::Chromatic, Rgb888::RED),
+];
+
+/// Black, white and yellow palette for [`TriColor`] displays
+pub const BLACK_WHITE_YELLOW: [(TriColor, Rgb888); 3] = [
+    (TriColor::Black, Rgb888::BLACK),
+    (TriColor::White, Rgb888::WHITE),
+    (TriColor::Chromatic, Rgb888::YELLOW),
+];
+
+/// The seven colors of [`OctColor`] displays, without [`OctColor::HiZ`]
+pub const SEVEN_COLOR: [(OctColor, Rgb888); 7] = [
+    (OctColor::Black, Rgb888::new(0x00, 0x00, 0x00)),
+    (OctColor::White, Rgb888::new(0xff, 0xff, 0xff)),
+    (OctColor::Green, Rgb888::new(0x00, 0xff, 0x00)),
+    (OctColor::Blue, Rgb888::new(0x00, 0x00, 0xff)),
+    (OctColor::Red, Rgb888::new(0xff, 0x00, 0x00)),
+    (OctColor::Yellow, Rgb888::new(0xff, 0xff, 0x00)),
+    (OctColor::Orange, Rgb888::new(0xff, 0x80, 0x00)),
+];
+
+/// How the quantization error is spread to the neighbouring pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Floyd–Steinberg, spreads the error over the next pixel and three pixels of the next row
+    FloydSteinberg,
+    /// Atkinson, spreads only 3/4 of the error which keeps more contrast
+    Atkinson,
+    /// Stucki, spreads the error over two rows for smoother results
+    Stucki,
+}
+
+impl Kernel {
+    // (dx, dy, weight) and the divisor of the weights
+    fn weights(self) -> (&'static [(i32, usize, i32)], i32) {
+        match self {
+            Kernel::FloydSteinberg => (&[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)], 16),
+            Kernel::Atkinson => (
+                &[
+                    (1, 0, 1),
+                    (2, 0, 1),
+                    (-1, 1, 1),
+                    (0, 1, 1),
+                    (1, 1, 1),
+                    (0, 2, 1),
+                ],
+                8,
+            ),
+            Kernel::Stucki => (
+                &[
+                    (1, 0, 8),
+                    (2, 0, 4),
+                    (-2, 1, 2),
+                    (-1, 1, 4),
+                    (0, 1, 8),
+                    (1, 1, 4),
+                    (2, 1, 2),
+                    (-2, 2, 1),
+                    (-1, 2, 2),
+                    (0, 2, 4),
+                    (1, 2, 2),
+                    (2, 2, 1),
+                ],
+                42,
+            ),
+        }
+    }
+}
+
+/// Error found while dithering
+#[derive(Debug, PartialEq, Eq)]
+pub enum DitherError<E> {
+    /// The error buffer is too small for the image width, see [`Ditherer::errors_len`]
+    BufferTooSmall,
+    /// The palette has no colors to draw with
+    EmptyPalette,
+    /// The draw target returned an error
+    Draw(E),
+}
+
+// pixels of padding on each side of an error row, the kernels reach two pixels away
+const PADDING: usize = 2;
+// rows of errors kept, the kernels reach two rows down
+const ROWS: usize = 3;
+
+/// Dithers images into a palette of display colors
+pub struct Ditherer<'a, C> {
+    kernel: Kernel,
+    palette: &'a [(C, Rgb888)],
+    errors: &'a mut [i16],
+}
+
+impl<'a, C: PixelColor> Ditherer<'a, C> {
+    /// Creates a ditherer for the colors of `palette`
+    ///
+    /// `errors` keeps the error of the rows being dithered, it needs
+    /// [`errors_len`](Ditherer::errors_len) values for the width of the images.
+    pub fn new(kernel: Kernel, palette: &'a [(C, Rgb888)], errors: &'a mut [i16]) -> Self {
+        Ditherer {
+            kernel,
+            palette,
+            errors,
+        }
+    }
+
+    /// Length of the error buffer needed for images of `width` pixels
+    pub const fn errors_len(width: u32) -> usize {
+        ROWS * (width as usize + 2 * PADDING) * 3
+    }
+
+    /// Dithers an image of `width` pixels per row and draws it with its top left corner at
+    /// `top_left`
+    ///
+    /// `pixels` are the pixels of the image row after row, the image ends with the iterator.
+    /// Gray images work too as `Gray8` converts into `Rgb888`.
+    pub fn draw<D, I>(
+        &mut self,
+        target: &mut D,
+        top_left: Point,
+        width: u32,
+        pixels: I,
+    ) -> Result<(), DitherError<D::Error>>
+    where
+        D: DrawTarget<Color = C>,
+        I: IntoIterator,
+        I::Item: Into<Rgb888>,
+    {
+        if self.palette.is_empty() {
+            return Err(DitherError::EmptyPalette);
+        }
+        if self.errors.len() < Self::errors_len(width) {
+            return Err(DitherError::BufferTooSmall);
+        }
+        // rows without pixels would never end
+        if width == 0 {
+            return Ok(());
+        }
+        let row_len = (width as usize + 2 * PADDING) * 3;
+        let errors = &mut self.errors[..ROWS * row_len];
+        errors.fill(0);
+        let (weights, divisor) = self.kernel.weights();
+        let palette = self.palette;
+
+        let mut pixels = pixels.into_iter();
+        for y in 0.. {
+            let mut drawn = 0;
+            let row = (0..width as usize).map_while(|x| {
+                let color: Rgb888 = pixels.next()?.into();
+                drawn += 1;
+
+                let index = (y % ROWS) * row_len + (x + PADDING) * 3;
+                let wanted = [
+                    color.r() as i16 + errors[index],
+                    color.g() as i16 + errors[index + 1],
+                    color.b() as i16 + errors[index + 2],
+                ]
+                .map(|c| c.clamp(0, 255));
+                // always found, the palette isn't empty
+                let (color, rgb) = nearest(palette, wanted)?;
+                let error = [
+                    wanted[0] - rgb.r() as i16,
+                    wanted[1] - rgb.g() as i16,
+                    wanted[2] - rgb.b() as i16,
+                ];
+
+                for &(dx, dy, weight) in weights {
+                    let index =
+                        ((y + dy) % ROWS) * row_len + (x as i32 + dx + PADDING as i32) as usize * 3;
+                    for (c, e) in error.iter().enumerate() {
+                        errors[index + c] += (*e as i32 * weight / divisor) as i16;
+                    }
+                }
+                Some(Pixel(top_left + Point::new(x as i32, y as i32), color))
+            });
+            target.draw_iter(row).map_err(DitherError::Draw)?;
+
+            if drawn < width {
+                return Ok(());
+            }
+            // the row is done, reuse it for the errors of the row after the next ones
+            errors[(y % ROWS) * row_len..][..row_len].fill(0);
+        }
+        Ok(())
+    }
+}
+
+// 8x8 Bayer threshold matrix
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// `DrawTarget` adapter drawing `Rgb888` or `Gray8` pixels on any display with ordered dithering
+///
+/// Every pixel is offset by a threshold depending on its position and then mapped to the closest
+/// color of the palette, so gradients become patterns instead of hard steps. As it works pixel
+/// by pixel it doesn't need any buffer.
+///
+///```rust
+///use embedded_graphics::{pixelcolor::Rgb888, prelude::*, primitives::*};
+///use epd_waveshare::{dither::{self, OrderedDither}, epd2in13bc::Display2in13bc, prelude::*};
+///
+///let mut display = Display2in13bc::default();
+///let mut dithered = OrderedDither::<_, Rgb888>::new(&mut display, &dither::BLACK_WHITE_RED);
+///let _ = Circle::new(Point::new(10, 10), 40)
+///    .into_styled(PrimitiveStyle::with_fill(Rgb888::new(255, 128, 128)))
+///    .draw(&mut dithered);
+///```
+pub struct OrderedDither<'a, D: DrawTarget, IN = Rgb888> {
+    target: &'a mut D,
+    palette: &'a [(D::Color, Rgb888)],
+    spread: i16,
+    _input: PhantomData<IN>,
+}
+
+impl<'a, D: DrawTarget, IN> OrderedDither<'a, D, IN> {
+    /// Wraps `target`, drawing with the colors of `palette`
+    ///
+    /// Pixels aren't drawn if the palette is empty.
+    pub fn new(target: &'a mut D, palette: &'a [(D::Color, Rgb888)]) -> Self {
+        OrderedDither {
+            target,
+            palette,
+            spread: 255,
+            _input: PhantomData,
+        }
+    }
+
+    /// Set the range of the threshold offsets, 255 by default
+    ///
+    /// Smaller values give less noise for palettes with many colors, like [`SEVEN_COLOR`].
+    pub fn set_spread(&mut self, spread: u8) {
+        self.spread = spread as i16;
+    }
+
+    /// Get the wrapped display back
+    pub fn into_inner(self) -> &'a mut D {
+        self.target
+    }
+}
+
+impl<'a, D: DrawTarget, IN> Dimensions for OrderedDither<'a, D, IN> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<'a, D, IN> DrawTarget for OrderedDither<'a, D, IN>
+where
+    D: DrawTarget,
+    IN: PixelColor + Into<Rgb888>,
+{
+    type Color = IN;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let palette = self.palette;
+        let spread = self.spread;
+        self.target
+            .draw_iter(pixels.into_iter().filter_map(|Pixel(point, color)| {
+                let threshold =
+                    BAYER[point.y.rem_euclid(8) as usize][point.x.rem_euclid(8) as usize] as i16;
+                // centered offset in (-spread / 2, spread / 2)
+                let offset = (threshold * 2 + 1 - 64) * spread / 128;
+                let rgb: Rgb888 = color.into();
+                let wanted = [rgb.r(), rgb.g(), rgb.b()].map(|c| (c as i16 + offset).clamp(0, 255));
+                nearest(palette, wanted).map(|(color, _)| Pixel(point, color))
+            }))
+    }
+}
+
+// palette entry closest to `wanted`, weighting the channels like the eye
+fn nearest<C: Copy>(palette: &[(C, Rgb888)], wanted: [i16; 3]) -> Option<(C, Rgb888)> {
+    let distance = |rgb: &Rgb888| {
+        let dr = (wanted[0] - rgb.r() as i16) as i32;
+        let dg = (wanted[1] - rgb.g() as i16) as i32;
+        let db = (wanted[2] - rgb.b() as i16) as i32;
+        3 * dr * dr + 4 * dg * dg + 2 * db * db
+    };
+    palette.iter().min_by_key(|(_, rgb)| distance(rgb)).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Display;
+    use embedded_graphics_core::pixelcolor::Gray8;
+
+    #[test]
+    fn gray_is_half_black() {
+        for kernel in [Kernel::FloydSteinberg, Kernel::Atkinson, Kernel::Stucki] {
+            let mut display = Display::<16, 16, false, 32, Color>::default();
+            let mut errors = [0; Ditherer::<Color>::errors_len(16)];
+            let mut ditherer = Ditherer::new(kernel, &BLACK_WHITE, &mut errors);
+
+            let gray = core::iter::repeat_n(Gray8::new(128), 16 * 16);
+            ditherer
+                .draw(&mut display, Point::zero(), 16, gray)
+                .unwrap();
+
+            let white: u32 = display.buffer().iter().map(|b| b.count_ones()).sum();
+            assert!((112..=144).contains(&white), "{:?}: {}", kernel, white);
+        }
+    }
+
+    #[test]
+    fn measured_palette_colors_stay_exact() {
+        let red = Rgb888::new(190, 60, 50);
+        let mut palette = SEVEN_COLOR;
+        palette[4].1 = red;
+
+        let mut display = Display::<4, 2, false, 4, OctColor>::default();
+        let mut errors = [0; Ditherer::<OctColor>::errors_len(4)];
+        let mut ditherer = Ditherer::new(Kernel::Stucki, &palette, &mut errors);
+        ditherer
+            .draw(&mut display, Point::zero(), 4, core::iter::repeat_n(red, 8))
+            .unwrap();
+
+        assert_eq!(display.buffer(), &[0x44; 4]);
+    }
+
+    #[test]
+    fn errors_buffer_too_small() {
+        let mut display = Display::<16, 16, false, 32, Color>::default();
+        let mut errors = [0; 16];
+        let mut ditherer = Ditherer::new(Kernel::Atkinson, &BLACK_WHITE, &mut errors);
+        assert_eq!(
+            ditherer.draw(&mut display, Point::zero(), 16, [Rgb888::WHITE]),
+            Err(DitherError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn empty_images_and_palettes() {
+        let mut display = Display::<16, 16, false, 32, Color>::default();
+        let mut errors = [0; Ditherer::<Color>::errors_len(16)];
+        let mut ditherer = Ditherer::new(Kernel::Atkinson, &BLACK_WHITE, &mut errors);
+        assert_eq!(
+            ditherer.draw(&mut display, Point::zero(), 0, [Rgb888::WHITE]),
+            Ok(())
+        );
+        assert_eq!(
+            ditherer.draw(
+                &mut display,
+                Point::zero(),
+                16,
+                core::iter::empty::<Rgb888>()
+            ),
+            Ok(())
+        );
+
+        let mut ditherer = Ditherer::<Color>::new(Kernel::Atkinson, &[], &mut errors);
+        assert_eq!(
+            ditherer.draw(&mut display, Point::zero(), 16, [Rgb888::WHITE]),
+            Err(DitherError::EmptyPalette)
+        );
+    }
+
+    #[test]
+    fn ordered_gray_is_half_black() {
+        let mut display = Display::<16, 16, false, 32, Color>::default();
+        let mut dithered = OrderedDither::<_, Gray8>::new(&mut display, &BLACK_WHITE);
+        dithered
+            .fill_solid(
+                &Rectangle::new(Point::zero(), Size::new(16, 16)),
+                Gray8::new(128),
+            )
+            .unwrap();
+
+        let white: u32 = display.buffer().iter().map(|b| b.count_ones()).sum();
+        assert_eq!(white, 128);
+    }
+
+    #[test]
+    fn ordered_keeps_palette_colors() {
+        let mut display = Display::<8, 8, false, 16, TriColor>::default();
+        let mut dithered = OrderedDither::<_, Rgb888>::new(&mut display, &BLACK_WHITE_RED);
+        dithered.clear(Rgb888::RED).unwrap();
+
+        assert_eq!(display.chromatic_buffer(), &[0xFF; 8]);
+    }
+}
@@ -0,0 +1,412 @@
+//! Keeping the last shown frame for [`QuickRefresh`]
+//!
+//! Quick refreshes need the frame that is currently on the panel as the "old" frame next to the
+//! new one. A [`DoubleBufferedDisplay`] is drawn on like a [`Display`] and keeps a copy of the
+//! last frame it showed, so it can send both frames in the right order and remember the new one
+//! once it is displayed.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use embedded_graphics::{prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+//!use epd_waveshare::{epd4in2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!let mut epd = Epd4in2::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
+//!let mut display = Box::new(DoubleBufferedDisplay4in2::default());
+//!
+//!// Start with a full refresh, the panel content is unknown
+//!display.clear(Color::White).ok();
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!display.mark_displayed();
+//!
+//!// Then refresh quickly
+//!epd.set_lut(&mut spi, &mut delay, Some(RefreshLut::Quick))?;
+//!Rectangle::new(Point::new(16, 16), Size::new(32, 32))
+//!    .into_styled(PrimitiveStyle::with_fill(Color::Black))
+//!    .draw(&mut *display)
+//!    .ok();
+//!display.update_and_display_new_frame(&mut epd, &mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::color::ColorType;
+use crate::diff::{DiffUpdate, FrameDiff};
+use crate::error::EpdError;
+use crate::graphics::{copy_window, line_bytes, paste_window, Display};
+use crate::traits::QuickRefresh;
+
+/// A [`Display`] that remembers the frame last shown with [`QuickRefresh`]
+///
+/// Draw on it like on a [`Display`], it implements [`DrawTarget`] and gives access to the
+/// display with [`display`](DoubleBufferedDisplay::display) and
+/// [`display_mut`](DoubleBufferedDisplay::display_mut). Both frames start out with every bit
+/// cleared; after showing a frame without this type, e.g. with a full refresh, call
+/// [`mark_displayed`](DoubleBufferedDisplay::mark_displayed).
+pub struct DoubleBufferedDisplay<
+    const WIDTH: u32,
+    const HEIGHT: u32,
+    const BWRBIT: bool,
+    const BYTECOUNT: usize,
+    COLOR: ColorType,
+> {
+    display: Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>,
+    shown: [u8; BYTECOUNT],
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType,
+    > Default for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    // inline is necessary here to allow heap allocation via Box on stack limited programs
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            display: Display::default(),
+            shown: [0u8; BYTECOUNT],
+        }
+    }
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType,
+    > DrawTarget for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    type Color = COLOR;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.display.draw_iter(pixels)
+    }
+}
+
+/// For use with embedded_grahics
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType,
+    > OriginDimensions for DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    fn size(&self) -> Size {
+        self.display.size()
+    }
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType,
+    > DoubleBufferedDisplay<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    /// The display that is drawn on
+    pub fn display(&self) -> &Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR> {
+        &self.display
+    }
+
+    /// The display that is drawn on, e.g. to change its rotation
+    pub fn display_mut(&mut self) -> &mut Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR> {
+        &mut self.display
+    }
+
+    /// Buffer of the frame being drawn
+    pub fn buffer(&self) -> &[u8] {
+        self.display.buffer()
+    }
+
+    /// Buffer of the frame last shown
+    pub fn shown_buffer(&self) -> &[u8] {
+        &self.shown
+    }
+
+    /// Records that the frame being drawn is now on the panel
+    pub fn mark_displayed(&mut self) {
+        self.shown.copy_from_slice(self.display.buffer());
+    }
+
+    /// Sends the last shown frame as the old frame and the drawn one as the new frame, and shows
+    /// the new one
+    pub fn update_and_display_new_frame<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        epd.update_old_frame(spi, &self.shown, delay)?;
+        epd.update_new_frame(spi, self.display.buffer(), delay)?;
+        epd.display_new_frame(spi, delay)?;
+        self.mark_displayed();
+        Ok(())
+    }
+
+    /// Like [`update_and_display_new_frame`](DoubleBufferedDisplay::update_and_display_new_frame),
+    /// but only for `window`
+    ///
+    /// The window is in the coordinates of the unrotated display, its left edge has to be a
+    /// multiple of 8. Both frames of the window are copied into `scratch` in turn, which needs
+    /// room for the window's bytes. Only the window is remembered as shown.
+    pub fn update_and_display_partial_new_frame<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        window: Rectangle,
+        scratch: &mut [u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let (x, y) = (window.top_left.x, window.top_left.y);
+        let (width, height) = (window.size.width, window.size.height);
+        if x < 0 || y < 0 || x % 8 != 0 || x as u32 + width > WIDTH || y as u32 + height > HEIGHT {
+            return Err(EpdError::InvalidWindow);
+        }
+        let (x, y) = (x as u32, y as u32);
+
+        let len = line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+            * height as usize
+            * COLOR::BUFFER_COUNT;
+        if scratch.len() < len {
+            return Err(EpdError::BufferSize {
+                expected: len,
+                actual: scratch.len(),
+            });
+        }
+        let scratch = &mut scratch[..len];
+
+        copy_window::<COLOR>(&self.shown, WIDTH, &window, scratch);
+        epd.update_partial_old_frame(spi, delay, scratch, x, y, width, height)?;
+        copy_window::<COLOR>(self.display.buffer(), WIDTH, &window, scratch);
+        epd.update_partial_new_frame(spi, delay, scratch, x, y, width, height)?;
+        epd.display_new_frame(spi, delay)?;
+        paste_window::<COLOR>(&mut self.shown, WIDTH, &window, scratch);
+        Ok(())
+    }
+
+    /// Sends only the regions that differ from the last shown frame, using [`FrameDiff`], and
+    /// shows the new frame
+    ///
+    /// `scratch` is used like in [`FrameDiff::update_quick_frames`]. Nothing is sent or refreshed
+    /// if the frames are the same.
+    pub fn update_and_display_changes<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<DiffUpdate, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let diff = FrameDiff::<COLOR>::new(WIDTH, HEIGHT);
+        let update =
+            diff.update_quick_frames(epd, spi, delay, &self.shown, self.display.buffer(), scratch)?;
+        if update != DiffUpdate::Unchanged {
+            epd.display_new_frame(spi, delay)?;
+            self.mark_displayed();
+        }
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use embedded_hal_mock::{delay, pin, spi, MockError};
+
+    // Keeps the RAM of a controller with room for 16x4 pixels
+    #[derive(Default)]
+    struct Ram {
+        old: [u8; 8],
+        new: [u8; 8],
+        refreshes: usize,
+    }
+
+    impl QuickRefresh<spi::Mock, pin::Mock, pin::Mock, pin::Mock, pin::Mock, delay::MockNoop> for Ram {
+        fn update_old_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            buffer: &[u8],
+            _: &mut delay::MockNoop,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            self.old.copy_from_slice(buffer);
+            Ok(())
+        }
+
+        fn update_new_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            buffer: &[u8],
+            _: &mut delay::MockNoop,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            self.new.copy_from_slice(buffer);
+            Ok(())
+        }
+
+        fn display_new_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            _: &mut delay::MockNoop,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            self.refreshes += 1;
+            Ok(())
+        }
+
+        fn update_and_display_new_frame(
+            &mut self,
+            spi: &mut spi::Mock,
+            buffer: &[u8],
+            delay: &mut delay::MockNoop,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            self.update_new_frame(spi, buffer, delay)?;
+            self.display_new_frame(spi, delay)
+        }
+
+        fn update_partial_old_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            _: &mut delay::MockNoop,
+            buffer: &[u8],
+            x: u32,
+            y: u32,
+            width: u32,
+            height: u32,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            let window = Rectangle::new(Point::new(x as i32, y as i32), Size::new(width, height));
+            paste_window::<Color>(&mut self.old, 16, &window, buffer);
+            Ok(())
+        }
+
+        fn update_partial_new_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            _: &mut delay::MockNoop,
+            buffer: &[u8],
+            x: u32,
+            y: u32,
+            width: u32,
+            height: u32,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            let window = Rectangle::new(Point::new(x as i32, y as i32), Size::new(width, height));
+            paste_window::<Color>(&mut self.new, 16, &window, buffer);
+            Ok(())
+        }
+
+        fn clear_partial_frame(
+            &mut self,
+            _: &mut spi::Mock,
+            _: &mut delay::MockNoop,
+            _: u32,
+            _: u32,
+            _: u32,
+            _: u32,
+        ) -> Result<(), EpdError<MockError, MockError>> {
+            Err(EpdError::Unsupported)
+        }
+    }
+
+    #[test]
+    fn old_frame_follows_shown_frame() {
+        let mut spi = spi::Mock::new(&[]);
+        let mut delay = delay::MockNoop::new();
+        let mut ram = Ram::default();
+        let mut display = DoubleBufferedDisplay::<16, 4, false, 8, Color>::default();
+        let mut scratch = [0u8; 8];
+
+        display.clear(Color::White).unwrap();
+        display
+            .update_and_display_new_frame(&mut ram, &mut spi, &mut delay)
+            .unwrap();
+        assert_eq!(ram.old, [0x00; 8]);
+        assert_eq!(ram.new, [0xFF; 8]);
+
+        display
+            .display_mut()
+            .set_pixel(Pixel(Point::new(9, 2), Color::Black));
+        let window = Rectangle::new(Point::new(8, 1), Size::new(8, 2));
+        display
+            .update_and_display_partial_new_frame(
+                &mut ram,
+                &mut spi,
+                &mut delay,
+                window,
+                &mut scratch,
+            )
+            .unwrap();
+        assert_eq!(ram.old, [0x00, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00]);
+        assert_eq!(ram.new, [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xBF, 0xFF, 0xFF]);
+        assert_eq!(display.shown_buffer(), display.buffer());
+
+        let update = display
+            .update_and_display_changes(&mut ram, &mut spi, &mut delay, &mut scratch)
+            .unwrap();
+        assert_eq!(update, DiffUpdate::Unchanged);
+        assert_eq!(ram.refreshes, 2);
+
+        let window = Rectangle::new(Point::new(4, 0), Size::new(8, 1));
+        assert!(matches!(
+            display.update_and_display_partial_new_frame(
+                &mut ram,
+                &mut spi,
+                &mut delay,
+                window,
+                &mut scratch
+            ),
+            Err(EpdError::InvalidWindow)
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+//! Adapters for peripherals implementing [embedded-hal 1.0](https://docs.rs/embedded-hal/1.0)
+//!
+//! The drivers are built on the embedded-hal 0.2 traits. The wrappers in this module implement
+//! those traits on top of embedded-hal 1.0 peripherals, so every driver can be used with a
+//! [`SpiDevice`] on a shared bus:
+//!
+//! - [`Spi`] wraps a `SpiDevice`. Chip select is handled by the bus manager, so pass [`NoCs`]
+//!   instead of a CS pin.
+//! - [`Pin`] wraps the BUSY, DC and RST pins. Their errors are converted to
+//!   [`ErrorKind`] so that all pins share one error type.
+//! - [`Delay`] wraps a [`DelayNs`] implementation.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!use epd_waveshare::{eh1::*, epd2in9_v2::Epd2in9, prelude::*};
+//!
+//!fn setup<SPI, BUSY, DC, RST, DELAY>(
+//!    spi: SPI,
+//!    busy: BUSY,
+//!    dc: DC,
+//!    rst: RST,
+//!    delay: DELAY,
+//!) -> Result<(), EpdError<SPI::Error, ErrorKind>>
+//!where
+//!    SPI: embedded_hal_1::spi::SpiDevice,
+//!    BUSY: embedded_hal_1::digital::InputPin,
+//!    DC: embedded_hal_1::digital::OutputPin,
+//!    RST: embedded_hal_1::digital::OutputPin,
+//!    DELAY: embedded_hal_1::delay::DelayNs,
+//!{
+//!    let mut spi = Spi::new(spi);
+//!    let mut delay = Delay::new(delay);
+//!    let mut epd = Epd2in9::new(
+//!        &mut spi,
+//!        NoCs,
+//!        Pin::new(busy),
+//!        Pin::new(dc),
+//!        Pin::new(rst),
+//!        &mut delay,
+//!        None,
+//!    )?;
+//!    epd.clear_frame(&mut spi, &mut delay)?;
+//!    epd.display_frame(&mut spi, &mut delay)?;
+//!    epd.sleep(&mut spi, &mut delay)
+//!}
+//!```
+
+use core::cell::RefCell;
+use embedded_hal::{
+    blocking::{delay::DelayUs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+use embedded_hal_1::{delay::DelayNs, digital, spi::SpiDevice};
+
+pub use embedded_hal_1::digital::ErrorKind;
+
+/// Wraps an embedded-hal 1.0 [`SpiDevice`]
+///
+/// Every write is sent as its own transaction, with the chip select managed by the device.
+pub struct Spi<S> {
+    device: S,
+}
+
+impl<S: SpiDevice> Spi<S> {
+    /// Wraps `device`
+    pub fn new(device: S) -> Self {
+        Spi { device }
+    }
+
+    /// Returns the wrapped device
+    pub fn into_inner(self) -> S {
+        self.device
+    }
+}
+
+impl<S: SpiDevice> Write<u8> for Spi<S> {
+    type Error = S::Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.device.write(words)
+    }
+}
+
+/// Wraps an embedded-hal 1.0 input or output pin
+pub struct Pin<P> {
+    // embedded-hal 1.0 reads pins through `&mut self`, embedded-hal 0.2 through `&self`
+    pin: RefCell<P>,
+}
+
+impl<P: digital::ErrorType> Pin<P> {
+    /// Wraps `pin`
+    pub fn new(pin: P) -> Self {
+        Pin {
+            pin: RefCell::new(pin),
+        }
+    }
+
+    /// Returns the wrapped pin
+    pub fn into_inner(self) -> P {
+        self.pin.into_inner()
+    }
+}
+
+impl<P: digital::OutputPin> OutputPin for Pin<P> {
+    type Error = ErrorKind;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin
+            .get_mut()
+            .set_low()
+            .map_err(|e| digital::Error::kind(&e))
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin
+            .get_mut()
+            .set_high()
+            .map_err(|e| digital::Error::kind(&e))
+    }
+}
+
+impl<P: digital::InputPin> InputPin for Pin<P> {
+    type Error = ErrorKind;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.pin
+            .borrow_mut()
+            .is_high()
+            .map_err(|e| digital::Error::kind(&e))
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.pin
+            .borrow_mut()
+            .is_low()
+            .map_err(|e| digital::Error::kind(&e))
+    }
+}
+
+/// Stand-in for the CS pin when the chip select is owned by a [`SpiDevice`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCs;
+
+impl OutputPin for NoCs {
+    type Error = ErrorKind;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Wraps an embedded-hal 1.0 [`DelayNs`] implementation
+pub struct Delay<D> {
+    delay: D,
+}
+
+impl<D: DelayNs> Delay<D> {
+    /// Wraps `delay`
+    pub fn new(delay: D) -> Self {
+        Delay { delay }
+    }
+
+    /// Returns the wrapped delay
+    pub fn into_inner(self) -> D {
+        self.delay
+    }
+}
+
+impl<D: DelayNs> DelayUs<u32> for Delay<D> {
+    fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal_1::spi::Operation;
+
+    #[derive(Default)]
+    struct RecordingDevice {
+        written: [u8; 8],
+        len: usize,
+        transactions: usize,
+    }
+
+    impl embedded_hal_1::spi::ErrorType for RecordingDevice {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice for RecordingDevice {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            self.transactions += 1;
+            for op in operations {
+                if let Operation::Write(words) = op {
+                    self.written[self.len..self.len + words.len()].copy_from_slice(words);
+                    self.len += words.len();
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn spi_write_is_one_transaction() {
+        let mut spi = Spi::new(RecordingDevice::default());
+        spi.write(&[1, 2, 3]).unwrap();
+        let device = spi.into_inner();
+        assert_eq!(device.transactions, 1);
+        assert_eq!(&device.written[..device.len], &[1, 2, 3]);
+    }
+}
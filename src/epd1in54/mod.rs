@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -60,12 +60,17 @@ use embedded_hal::{
 
 use crate::type_a::{
     command::Command,
-    constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
+    constants::{LUT_FAST_FULL_UPDATE, LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
 };
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::error::{check_buffer_len, check_lut_len, EpdError};
+use crate::lut::TypeALut;
+use crate::traits::{
+    Capabilities, RefreshLut, RefreshMode, WaveshareCustomLut, WaveshareDisplay,
+    WaveshareStreamingDisplay,
+};
 
 use crate::buffer_len;
 use crate::interface::DisplayInterface;
@@ -86,21 +91,25 @@ pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Color
     background_color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    /// Refresh mode selecting the LUT
+    refresh: RefreshMode,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
 
         // 3 Databytes:
         // A[7:0]
@@ -150,12 +159,27 @@ impl<SPI, CS, BUSY, DC, RST, E, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8, Error = E>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display1in54;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        fast_full_refresh: true,
+        custom_lut: true,
+        ..Capabilities::BASIC
+    };
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -172,13 +196,13 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
 
         let mut epd = Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
+            refresh: RefreshMode::Full,
         };
 
         epd.init(spi, delay)?;
@@ -186,11 +210,19 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
@@ -204,7 +236,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
@@ -222,7 +255,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, delay, x, y)?;
@@ -232,7 +265,19 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
@@ -246,18 +291,26 @@ where
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -278,23 +331,52 @@ where
         &self.background_color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match refresh_rate {
+            Some(RefreshLut::Full) => self.refresh = RefreshMode::Full,
+            Some(RefreshLut::Quick) => self.refresh = RefreshMode::Partial,
+            None => {}
         }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, delay, &LUT_FULL_UPDATE),
-            RefreshLut::Quick => self.set_lut_helper(spi, delay, &LUT_PARTIAL_UPDATE),
+        let lut = match self.refresh {
+            RefreshMode::FastFull => &LUT_FAST_FULL_UPDATE,
+            RefreshMode::Partial => &LUT_PARTIAL_UPDATE,
+            _ => &LUT_FULL_UPDATE,
+        };
+        self.set_lut_helper(spi, delay, lut)
+    }
+
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        if !self.supports_refresh_mode(mode) {
+            return Err(EpdError::Unsupported);
         }
+        self.refresh = mode;
+        self.set_lut(spi, delay, None)
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
@@ -303,16 +385,16 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -328,10 +410,11 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        if start_x >= end_x || start_y >= end_y {
+            return Err(EpdError::InvalidWindow);
+        }
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -361,7 +444,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -382,9 +465,9 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(buffer.len() == 30);
+        check_lut_len(buffer, 30)?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
@@ -392,6 +475,58 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareCustomLut<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    type Lut = TypeALut;
+
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &TypeALut,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.set_lut_helper(spi, delay, &lut.to_bytes())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.use_full_frame(spi, delay)?;
+        self.interface.cmd(spi, Command::WriteRam)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -402,4 +537,42 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd1in54<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn refresh_modes() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd1in54::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        assert_eq!(sim.params(0x32).as_deref(), Some(&LUT_FULL_UPDATE[..]));
+
+        assert!(epd.supports_refresh_mode(RefreshMode::FastFull));
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::FastFull)
+            .unwrap();
+        assert_eq!(sim.params(0x32).as_deref(), Some(&LUT_FAST_FULL_UPDATE[..]));
+
+        assert!(!epd.supports_refresh_mode(RefreshMode::Grayscale));
+        assert_eq!(
+            epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Grayscale),
+            Err(EpdError::Unsupported)
+        );
+        assert_eq!(sim.params(0x32).as_deref(), Some(&LUT_FAST_FULL_UPDATE[..]));
+    }
 }
@@ -17,13 +17,19 @@ use embedded_hal::{
 
 use crate::type_a::command::Command;
 
-mod constants;
+pub(crate) mod constants;
 use crate::epd1in54_v2::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::error::{check_buffer_len, check_lut_len, EpdError};
+use crate::lut::Ssd1680Lut;
+use crate::traits::{
+    Capabilities, RefreshLut, RefreshMode, WaveshareCustomLut, WaveshareDisplay,
+    WaveshareStreamingDisplay,
+};
 
+use crate::buffer_len;
 use crate::interface::DisplayInterface;
 
 #[cfg(feature = "graphics")]
@@ -44,13 +50,17 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd(spi, Command::SwReset)?;
         self.wait_until_idle(spi, delay)?;
@@ -95,12 +105,26 @@ impl<SPI, CS, BUSY, DC, RST, E, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8, Error = E>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display1in54;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        custom_lut: true,
+        ..Capabilities::BASIC
+    };
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -117,7 +141,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
 
         let mut epd = Epd1in54 {
@@ -131,11 +155,19 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
@@ -147,7 +179,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
         self.interface
@@ -165,7 +198,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.set_ram_area(spi, delay, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, delay, x, y)?;
@@ -175,7 +208,19 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         if self.refresh == RefreshLut::Full {
             self.interface
@@ -192,18 +237,26 @@ where
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.use_full_frame(spi, delay)?;
 
@@ -227,12 +280,20 @@ where
         &self.background_color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -260,8 +321,25 @@ where
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match mode {
+            RefreshMode::Full => self.set_lut(spi, delay, Some(RefreshLut::Full)),
+            RefreshMode::Partial => self.set_lut(spi, delay, Some(RefreshLut::Quick)),
+            _ => Err(EpdError::Unsupported),
+        }
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
@@ -270,16 +348,16 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     pub(crate) fn use_full_frame(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, delay, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -295,10 +373,11 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        if start_x >= end_x || start_y >= end_y {
+            return Err(EpdError::InvalidWindow);
+        }
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -328,7 +407,7 @@ where
         delay: &mut DELAY,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -349,9 +428,9 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
-        assert!(buffer.len() == 159);
+        check_lut_len(buffer, 159)?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, &buffer[0..153])?;
@@ -376,6 +455,58 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareCustomLut<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    type Lut = Ssd1680Lut;
+
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &Ssd1680Lut,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.set_lut_helper(spi, delay, &lut.to_bytes())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.use_full_frame(spi, delay)?;
+        self.interface.cmd(spi, Command::WriteRam)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -386,4 +517,10 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd1in54<_, _, _, _, _, _>>();
+    }
 }
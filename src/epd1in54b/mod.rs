@@ -5,9 +5,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    Capabilities, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareStreamingDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 //The Lookup Tables for the Display
@@ -22,21 +24,46 @@ pub const HEIGHT: u32 = 200;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
 
-use crate::color::Color;
+// Parameters of `PartialWindow`, or None if the window is out of bounds or `x` and `width` aren't
+// multiples of 8
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> Option<[u8; 7]> {
+    if !x.is_multiple_of(8)
+        || !width.is_multiple_of(8)
+        || width == 0
+        || height == 0
+        || x + width > WIDTH
+        || y + height > HEIGHT
+    {
+        return None;
+    }
+    let x_end = x + width - 1;
+    let y_end = y + height - 1;
+    Some([
+        x as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        // gates scan both inside and outside of the window
+        0x01,
+    ])
+}
+
+use crate::color::{Color, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 1in54b EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
 #[cfg(feature = "graphics")]
 pub type Display1in54b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    true,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd1in54b driver
@@ -50,13 +77,17 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 10_000);
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.reset(delay, 10_000, 10_000)?;
 
         // set the power settings
         self.interface
@@ -97,9 +128,9 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
@@ -108,7 +139,9 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -118,7 +151,8 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -136,11 +170,53 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
         Ok(())
     }
+
+    fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(black, buffer_len(width as usize, height as usize))?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        // Two bits per pixel
+        self.interface.data_mapped(spi, black, expand_bits)?;
+        self.command(spi, Command::PartialOut)
+    }
+
+    fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(chromatic, buffer_len(width as usize, height as usize))?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
+        self.command(spi, Command::PartialOut)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
@@ -148,12 +224,25 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display1in54b;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize * 2);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        chromatic: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -162,7 +251,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -173,7 +262,11 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -193,7 +286,11 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
@@ -205,6 +302,14 @@ where
         &self.color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -218,17 +323,15 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        for b in buffer {
-            // Two bits per pixel
-            let expanded = expand_bits(*b);
-            self.interface.data(spi, &expanded)?;
-        }
+        // Two bits per pixel
+        self.interface.data_mapped(spi, buffer, expand_bits)?;
 
         //NOTE: Example code has a delay here
 
@@ -243,7 +346,8 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned. The red layer of the window is cleared.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -253,28 +357,58 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_achromatic_frame(spi, delay, buffer, x, y, width, height)?;
+
+        // Clear the red layer of the window
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.color.get_byte_value();
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color, width / 8 * height)?;
+        self.command(spi, Command::PartialOut)
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
@@ -299,7 +433,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface
             .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
         self.interface
@@ -318,8 +452,12 @@ where
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
@@ -328,16 +466,24 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -346,11 +492,11 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -362,6 +508,50 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.send_resolution(spi)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // Two bits per pixel
+        self.interface.data_mapped(spi, data, expand_bits)
+    }
+
+    fn end_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // Clear the read layer
+        let color = self.color.get_byte_value();
+        let nbits = WIDTH * (HEIGHT / 8);
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, nbits)
+    }
+}
+
 fn expand_bits(bits: u8) -> [u8; 2] {
     let mut x = bits as u16;
 
@@ -383,4 +573,59 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd1in54b<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_color_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT).bits_per_pixel(0, 2);
+        let mut spi = sim.spi();
+        let mut epd = Epd1in54b::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        // the black layer has two bits per pixel
+        // a 16x3 window at (8, 4) with a chromatic first row
+        let black = [0xFF; 2 * 3];
+        let chromatic = [0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+        epd.update_partial_color_frame(&mut spi, &mut SimDelay, &black, &chromatic, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(!sim.shown_pixel(0, 7, 4));
+        assert!(!sim.shown_pixel(0, 24, 4));
+        assert!(!sim.shown_pixel(0, 8, 3));
+        assert!(!sim.shown_pixel(0, 8, 7));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 4));
+        assert!(!sim.shown_pixel(1, 8, 5));
+        assert!(!sim.shown_pixel(1, 7, 4));
+        assert!(!sim.shown_pixel(1, 24, 4));
+
+        // only the black window, the chromatic one is cleared (all bits set)
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &black, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 6));
+        assert!(!sim.shown_pixel(1, 24, 4));
+    }
 }
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 //!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle, PrimitiveStyleBuilder}};
 //!use epd_waveshare::{epd2in13bc::*, prelude::*};
 //!#
@@ -55,9 +55,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    Capabilities, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareStreamingDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in13bc in pixels
@@ -77,6 +79,32 @@ const BLACK_BORDER: u8 = 0x30;
 const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 
+// Parameters of `PartialWindow`, or None if the window is out of bounds or `x` and `width` aren't
+// multiples of 8
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> Option<[u8; 7]> {
+    if !x.is_multiple_of(8)
+        || !width.is_multiple_of(8)
+        || width == 0
+        || height == 0
+        || x + width > WIDTH
+        || y + height > HEIGHT
+    {
+        return None;
+    }
+    let x_end = x + width - 1;
+    let y_end = y + height - 1;
+    Some([
+        x as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        // gates scan both inside and outside of the window
+        0x01,
+    ])
+}
+
 use crate::color::TriColor;
 
 pub(crate) mod command;
@@ -104,15 +132,19 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
 
         // start the booster
         self.interface
@@ -148,9 +180,9 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
@@ -159,7 +191,9 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -172,7 +206,8 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -186,13 +221,59 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            black,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission2,
+            chromatic,
+            x,
+            y,
+            width,
+            height,
+        )?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
@@ -200,12 +281,25 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = TriColor;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display2in13bc;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize * 2);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        chromatic: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -214,7 +308,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -225,7 +319,11 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -242,7 +340,11 @@ where
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
@@ -254,6 +356,14 @@ where
         &self.color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -267,7 +377,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         self.interface.data(spi, buffer)?;
@@ -282,7 +393,8 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned. The chromatic layer of the window is cleared.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -292,29 +404,70 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )?;
+
+        // Clear the chromatic layer of the window
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.color.get_byte_value();
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color, width / 8 * height)?;
+        self.command(spi, Command::PartialOut)?;
+
+        self.wait_until_idle(spi, delay)?;
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.command(spi, Command::DisplayRefresh)?;
 
-        self.wait_until_idle(spi, delay)?;
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.send_resolution(spi)?;
 
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
@@ -337,12 +490,16 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
@@ -351,16 +508,24 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -369,11 +534,32 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    // Writes one layer of a window
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_layer(
+        &mut self,
+        spi: &mut SPI,
+        layer: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.cmd_with_data(spi, layer, buffer)?;
+        self.command(spi, Command::PartialOut)
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -385,7 +571,11 @@ where
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let border = match color {
             TriColor::Black => BLACK_BORDER,
             TriColor::White => WHITE_BORDER,
@@ -398,3 +588,113 @@ where
         )
     }
 }
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn frame_passes(&self) -> usize {
+        2
+    }
+
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        if pass == 0 {
+            self.interface.cmd(spi, Command::DataStartTransmission1)
+        } else {
+            self.interface.cmd(spi, Command::DataStartTransmission2)
+        }
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn end_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 104);
+        assert_eq!(HEIGHT, 212);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd2in13bc<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_color_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in13bc::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        // a 16x3 window at (8, 4) with a chromatic first row
+        let black = [0xFF; 2 * 3];
+        let chromatic = [0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+        epd.update_partial_color_frame(&mut spi, &mut SimDelay, &black, &chromatic, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(!sim.shown_pixel(0, 7, 4));
+        assert!(!sim.shown_pixel(0, 24, 4));
+        assert!(!sim.shown_pixel(0, 8, 3));
+        assert!(!sim.shown_pixel(0, 8, 7));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 4));
+        assert!(!sim.shown_pixel(1, 8, 5));
+        assert!(!sim.shown_pixel(1, 7, 4));
+        assert!(!sim.shown_pixel(1, 24, 4));
+
+        // only the black window, the chromatic one is cleared (all bits set)
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &black, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 6));
+        assert!(!sim.shown_pixel(1, 24, 4));
+    }
+}
@@ -7,6 +7,7 @@ use crate::traits;
 /// For more infos about the addresses and what they are doing look into the pdfs
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
+#[rustfmt::skip]
 pub(crate) enum Command {
 	PanelSetting = 0x00,
 	PowerSetting = 0x01,
@@ -56,7 +56,6 @@ pub(crate) const LUT_FULL_BB: [u8; 42] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
-
 // Partial screen update
 
 #[rustfmt::skip]
@@ -114,4 +113,3 @@ pub(crate) const LUT_PART_BB: [u8; 42] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
-
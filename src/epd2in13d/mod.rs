@@ -15,16 +15,21 @@ use embedded_hal::{
 
 use crate::buffer_len;
 use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::lut::Il0373Lut;
+use crate::traits::{
+    Capabilities, InternalWiAdditions, RefreshLut, RefreshMode, WaveshareCustomLut,
+    WaveshareDisplay, WaveshareStreamingDisplay,
+};
 
 pub(crate) mod command;
 use command::Command;
 
 pub(crate) mod constants;
 use self::constants::{
-    LUT_FULL_VCOM, LUT_FULL_WW, LUT_FULL_BW, LUT_FULL_WB, LUT_FULL_BB,
-    LUT_PART_VCOM, LUT_PART_WW, LUT_PART_BW, LUT_PART_WB, LUT_PART_BB,
+    LUT_FULL_BB, LUT_FULL_BW, LUT_FULL_VCOM, LUT_FULL_WB, LUT_FULL_WW, LUT_PART_BB, LUT_PART_BW,
+    LUT_PART_VCOM, LUT_PART_WB, LUT_PART_WW,
 };
 
 /// Full size buffer for use with the 2in13 v2 EPD
@@ -62,16 +67,43 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn turn_on_display(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd(spi, Command::DisplayRefresh)?;
         delay.delay_us(100000); // can apparently be as low as 200us
         self.wait_until_idle(spi, delay)
     }
+
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
+        self.interface
+            .cmd_with_data(spi, Command::VcomLut, lut_vcom)?;
+        self.interface
+            .cmd_with_data(spi, Command::WhiteToWhiteLut, lut_ww)?;
+        self.interface
+            .cmd_with_data(spi, Command::BlackToWhiteLut, lut_bw)?;
+        self.interface
+            .cmd_with_data(spi, Command::WhiteToBlackLut, lut_wb)?;
+        self.interface
+            .cmd_with_data(spi, Command::BlackToBlackLut, lut_bb)
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -79,27 +111,43 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // HW reset
-        self.interface.reset(delay, 10_000, 10_000);
+        self.interface.reset(delay, 10_000, 10_000)?;
 
-        self.interface.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b, 0x03])?;
-        self.interface.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x03, 0x00, 0x2b, 0x2b, 0x03],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
         self.interface.cmd(spi, Command::PowerOn)?;
         self.wait_until_idle(spi, delay)?;
-        self.interface.cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0e])?;
-        self.interface.cmd_with_data(spi, Command::PllControl, &[0x3a])?;
-        self.interface.cmd_with_data(spi, Command::ResolutionSetting, &[
-            WIDTH as u8,
-            ((HEIGHT >> 8) & 0xff) as u8,
-            (HEIGHT & 0xff) as u8,
-        ])?;
-        self.interface.cmd_with_data(spi, Command::VcmDcSetting, &[0x28])?;
+        self.interface
+            .cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0e])?;
+        self.interface
+            .cmd_with_data(spi, Command::PllControl, &[0x3a])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[
+                WIDTH as u8,
+                ((HEIGHT >> 8) & 0xff) as u8,
+                (HEIGHT & 0xff) as u8,
+            ],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::VcmDcSetting, &[0x28])?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
@@ -111,12 +159,26 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display2in13;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        custom_lut: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -125,7 +187,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let mut epd = Epd2in13 {
             interface: DisplayInterface::new(cs, busy, dc, rst, delay_us),
             //sleep_mode: DeepSleepMode::Mode1,
@@ -137,11 +199,19 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, _spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         /*
         self.wait_until_idle(spi, delay)?;
 
@@ -166,22 +236,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
 
         self.refresh = RefreshLut::Full;
 
         let color = self.background_color.get_byte_value();
         const BUF_LEN: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
-        self.interface.cmd(spi, Command::DisplayStartTransmission1)?;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission1)?;
         self.interface.data_x_times(spi, color, BUF_LEN)?;
-        self.interface.cmd_with_data(spi, Command::DisplayStartTransmission2, &buffer)?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayStartTransmission2, buffer)?;
         Ok(())
     }
 
-    /// Updating only a part of the frame is not supported when using the
-    /// partial refresh feature. The function will panic if called when set to
-    /// use partial refresh.
+    /// `x` and `width` need to be multiples of 8, otherwise
+    /// [`EpdError::InvalidWindow`] is returned.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -191,10 +262,11 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!((width * height / 8) as usize == buffer.len());
-        assert!(x % 8 == 0);
-        assert!(width % 8 == 0);
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        if !x.is_multiple_of(8) || !width.is_multiple_of(8) {
+            return Err(EpdError::InvalidWindow);
+        }
 
         self.refresh = RefreshLut::Quick;
 
@@ -205,41 +277,69 @@ where
         self.interface.data(spi, &[(y / 256) as u8])?;
         self.interface.data(spi, &[(y % 256) as u8])?;
         self.interface.data(spi, &[((y + height) / 256) as u8])?;
-        self.interface.data(spi, &[((y + height) % 256 - 1) as u8])?;
+        self.interface
+            .data(spi, &[((y + height) % 256 - 1) as u8])?;
         self.interface.data(spi, &[0x28])?;
-        self.interface.cmd(spi, Command::DisplayStartTransmission1)?;
-        for ea_byte in buffer {
-            self.interface.data(spi, &[!ea_byte])?;
-        }
-        self.interface.cmd(spi, Command::DisplayStartTransmission2)?;
-        self.interface.data(spi, &buffer)?;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission1)?;
+        self.interface
+            .data_mapped(spi, buffer, |ea_byte| [!ea_byte])?;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission2)?;
+        self.interface.data(spi, buffer)?;
 
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.set_lut(spi, delay, Some(self.refresh))?;
-        self.turn_on_display(spi, delay)?;
+        self.interface.cmd(spi, Command::DisplayRefresh)?;
+        delay.delay_us(100000); // can apparently be as low as 200us
         Ok(())
     }
 
+    /// Sends `GetStatus` first, the controller only updates BUSY after it
+    fn is_busy(&mut self, spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface
+            .is_busy_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let color = self.background_color.get_byte_value();
         const BUF_LEN: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
-        self.interface.cmd(spi, Command::DisplayStartTransmission1)?;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission1)?;
         self.interface.data_x_times(spi, color, BUF_LEN)?;
-        self.interface.cmd(spi, Command::DisplayStartTransmission2)?;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission2)?;
         self.interface.data_x_times(spi, !color, BUF_LEN)?;
         self.set_lut(spi, delay, None)?;
         self.turn_on_display(spi, delay)?;
@@ -254,6 +354,14 @@ where
         &self.background_color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -267,30 +375,120 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let (vcom, ww, bw, wb, bb) = match refresh_rate {
-            Some(RefreshLut::Full) | None =>
-                (&LUT_FULL_VCOM, &LUT_FULL_WW, &LUT_FULL_BW, &LUT_FULL_WB, &LUT_FULL_BB),
+            Some(RefreshLut::Full) | None => (
+                &LUT_FULL_VCOM,
+                &LUT_FULL_WW,
+                &LUT_FULL_BW,
+                &LUT_FULL_WB,
+                &LUT_FULL_BB,
+            ),
             Some(RefreshLut::Quick) => {
-                self.interface.cmd_with_data(spi, Command::VcmDcSetting, &[0x00])?;
-                (&LUT_PART_VCOM, &LUT_PART_WW, &LUT_PART_BW, &LUT_PART_WB, &LUT_PART_BB)
+                self.interface
+                    .cmd_with_data(spi, Command::VcmDcSetting, &[0x00])?;
+                (
+                    &LUT_PART_VCOM,
+                    &LUT_PART_WW,
+                    &LUT_PART_BW,
+                    &LUT_PART_WB,
+                    &LUT_PART_BB,
+                )
             }
         };
 
-        self.interface.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xb7])?;
-        self.interface.cmd_with_data(spi, Command::VcomLut, vcom)?;
-        self.interface.cmd_with_data(spi, Command::WhiteToWhiteLut, ww)?;
-        self.interface.cmd_with_data(spi, Command::BlackToWhiteLut, bw)?;
-        self.interface.cmd_with_data(spi, Command::WhiteToBlackLut, wb)?;
-        self.interface.cmd_with_data(spi, Command::BlackToBlackLut, bb)
+        self.set_lut_helper(spi, vcom, ww, bw, wb, bb)
+    }
+
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match mode {
+            RefreshMode::Full => self.set_lut(spi, delay, Some(RefreshLut::Full)),
+            RefreshMode::Partial => self.set_lut(spi, delay, Some(RefreshLut::Quick)),
+            _ => Err(EpdError::Unsupported),
+        }
     }
 
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)?;
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface
+            .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)?;
         Ok(())
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareCustomLut<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    type Lut = Il0373Lut;
+
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &Il0373Lut,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.set_lut_helper(
+            spi,
+            &lut.vcom_bytes(),
+            &Il0373Lut::table_bytes(&lut.ww),
+            &Il0373Lut::table_bytes(&lut.bw),
+            &Il0373Lut::table_bytes(&lut.wb),
+            &Il0373Lut::table_bytes(&lut.bb),
+        )
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.refresh = RefreshLut::Full;
+
+        let color = self.background_color.get_byte_value();
+        const BUF_LEN: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
+        self.interface
+            .cmd(spi, Command::DisplayStartTransmission1)?;
+        self.interface.data_x_times(spi, color, BUF_LEN)?;
+        self.interface.cmd(spi, Command::DisplayStartTransmission2)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,4 +499,10 @@ mod tests {
         assert_eq!(HEIGHT, 212);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd2in13<_, _, _, _, _, _>>();
+    }
 }
@@ -0,0 +1,340 @@
+//! Async driver for the Waveshare 2.9" E-Ink Display V2
+//!
+//! Uses the same command sequences as the blocking [`Epd2in9`](super::Epd2in9).
+
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::{
+    RamWindow, BUSY_SIGNAL, DEFAULT_BACKGROUND_COLOR, DISPLAY_NEW_FRAME, FULL_RAM_AREA, HEIGHT,
+    INIT, PREPARE_NEW_FRAME, SLEEP, START_REFRESH, WIDTH,
+};
+use crate::async_interface::AsyncDisplayInterface;
+use crate::async_traits::{AsyncQuickRefresh, AsyncWaveshareDisplay};
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
+use crate::traits::RefreshLut;
+use crate::type_a::command::Command;
+
+/// Async Epd2in9 (V2) driver
+pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
+    /// SPI
+    interface: AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY>,
+    /// Color
+    background_color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, EpdError<SPI::Error, BUSY::Error>> {
+        let interface = AsyncDisplayInterface::new(busy, dc, rst);
+
+        let mut epd = Epd2in9 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+        };
+
+        epd.wake_up(spi, delay).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP).await
+    }
+
+    async fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT).await
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn set_busy_timeout(&mut self, max_polls: Option<u32>) {
+        self.interface.set_busy_timeout(max_polls);
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.write_ram_window(spi, delay, Command::WriteRam, buffer, x, y, width, height)
+            .await
+    }
+
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, START_REFRESH)
+            .await?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_frame(spi, buffer, delay).await?;
+        self.display_frame(spi, delay).await
+    }
+
+    async fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await?;
+
+        // clear the ram with the background color
+        let color = self.background_color.get_byte_value();
+
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await?;
+        self.interface.cmd(spi, Command::WriteRam2).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await
+    }
+
+    /// Does nothing, the full LUT is loaded by `display_frame` and the quick one by
+    /// `display_new_frame`
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        Ok(())
+    }
+
+    async fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncQuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    /// To be followed immediately by `update_new_frame`.
+    async fn update_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+            .await
+    }
+
+    /// To be used immediately after `update_old_frame`.
+    async fn update_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, PREPARE_NEW_FRAME)
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await
+    }
+
+    /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
+    async fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, DISPLAY_NEW_FRAME)
+            .await
+    }
+
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_new_frame(spi, buffer, delay).await?;
+        self.display_new_frame(spi, delay).await
+    }
+
+    /// To be followed by `update_partial_new_frame` for the same window.
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.write_ram_window(spi, delay, Command::WriteRam2, buffer, x, y, width, height)
+            .await
+    }
+
+    /// Loads the partial LUT like `update_new_frame`, so the window is shown with
+    /// `display_new_frame`.
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, PREPARE_NEW_FRAME)
+            .await?;
+
+        self.write_ram_window(spi, delay, Command::WriteRam, buffer, x, y, width, height)
+            .await
+    }
+
+    /// Clears the window in both rams with the background color
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        let window = RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.background_color.get_byte_value();
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, &window.clear(color))
+            .await?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, FULL_RAM_AREA)
+            .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    // Writes `buffer` into a window of one of the rams and selects the whole ram again
+    #[allow(clippy::too_many_arguments)]
+    async fn write_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        ram: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        let window = RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, &window.write(ram, buffer))
+            .await?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, FULL_RAM_AREA)
+            .await
+    }
+}
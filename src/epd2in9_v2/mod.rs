@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -74,6 +74,29 @@ const LUT_PARTIAL_2IN9: [u8; 159] = [
     0x22, 0x0, 0x0, 0x0, 0x22, 0x17, 0x41, 0xB0, 0x32, 0x36,
 ];
 
+// 4-gray waveform from the Waveshare driver, without the voltages which are sent separately
+#[rustfmt::skip]
+const LUT_4GRAY: [u8; 153] = [
+    0x00, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L0
+    0x20, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L1
+    0x28, 0x60, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L2
+    0x2A, 0x60, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L3
+    0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L4
+    0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, // TP, SR, RP of Group0
+    0x1E, 0x1E, 0x00, 0x00, 0x00, 0x00, 0x01, // TP, SR, RP of Group1
+    0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, // TP, SR, RP of Group2
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group3
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group4
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group5
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group6
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group7
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group8
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group9
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group10
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group11
+    0x24, 0x22, 0x22, 0x22, 0x23, 0x32, 0x00, 0x00, 0x00, // FR, XON
+];
+
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
     digital::v2::*,
@@ -83,12 +106,204 @@ use crate::type_a::command::Command;
 
 use crate::color::Color;
 
+use crate::error::{check_buffer_len, EpdError};
+use crate::lut::Ssd1680Lut;
 use crate::traits::*;
 
 use crate::buffer_len;
 use crate::interface::DisplayInterface;
+use crate::sequence::{Busy, Step};
 use crate::traits::QuickRefresh;
 
+#[cfg(feature = "async")]
+pub mod asynch;
+
+const BUSY_SIGNAL: Busy<Command> = Busy {
+    is_busy_low: IS_BUSY_LOW,
+    status_command: None,
+};
+
+const INIT: &[Step<Command>] = &[
+    Step::Reset(10_000, 2_000),
+    Step::WaitUntilIdle,
+    Step::Cmd(Command::SwReset),
+    Step::WaitUntilIdle,
+    // 3 Databytes:
+    // A[7:0]
+    // 0.. A[8]
+    // 0.. B[2:0]
+    // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+    Step::CmdWithData(Command::DriverOutputControl, &[0x27, 0x01, 0x00]),
+    // One Databyte with default value 0x03
+    //  -> address: x increment, y increment, address counter is updated in x direction
+    Step::CmdWithData(Command::DataEntryModeSetting, &[0x03]),
+    Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &FULL_RAM_X),
+    Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &FULL_RAM_Y),
+    Step::CmdWithData(Command::DisplayUpdateControl1, &[0x00, 0x80]),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::SetRamXAddressCounter, &[0x00]),
+    Step::CmdWithData(Command::SetRamYAddressCounter, &[0x00, 0x00]),
+    Step::WaitUntilIdle,
+];
+
+const SLEEP: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
+    Step::CmdWithData(Command::DeepSleepMode, &[0x01]),
+];
+
+// actually is the "Turn on Display" sequence
+const START_REFRESH: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+    Step::CmdWithData(Command::DisplayUpdateControl2, &[0xF7]),
+    Step::Cmd(Command::MasterActivation),
+];
+
+// Loads the partial LUT and selects the full frame/ram, followed by writing the new frame
+const PREPARE_NEW_FRAME: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::Reset(10_000, 2_000),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::WriteLutRegister, &LUT_PARTIAL_2IN9),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(
+        Command::WriteOtpSelection,
+        &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+    ),
+    Step::CmdWithData(Command::BorderWaveformControl, &[0x80]),
+    Step::CmdWithData(Command::DisplayUpdateControl2, &[0xC0]),
+    Step::Cmd(Command::MasterActivation),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &FULL_RAM_X),
+    Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &FULL_RAM_Y),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::SetRamXAddressCounter, &[0x00]),
+    Step::CmdWithData(Command::SetRamYAddressCounter, &[0x00, 0x00]),
+];
+
+const INIT_4GRAY: &[Step<Command>] = &[
+    Step::Reset(10_000, 2_000),
+    Step::WaitUntilIdle,
+    Step::Cmd(Command::SwReset),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::DriverOutputControl, &[0x27, 0x01, 0x00]),
+    Step::CmdWithData(Command::DataEntryModeSetting, &[0x03]),
+    Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &FULL_RAM_X),
+    Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &FULL_RAM_Y),
+    Step::CmdWithData(Command::BorderWaveformControl, &[0x04]),
+    Step::CmdWithData(Command::SetRamXAddressCounter, &[0x00]),
+    Step::CmdWithData(Command::SetRamYAddressCounter, &[0x00, 0x00]),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::WriteLutRegister, &LUT_4GRAY),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::WriteLutRegisterEnd, &[0x22]),
+    Step::CmdWithData(Command::GateDrivingVoltage, &[0x17]),
+    Step::CmdWithData(Command::SourceDrivingVoltage, &[0x41, 0xAE, 0x32]),
+    Step::CmdWithData(Command::WriteVcomRegister, &[0x28]),
+];
+
+// Like START_REFRESH but keeps the 4-gray or custom LUT instead of loading the one from OTP
+const START_REFRESH_LUT_REGISTER: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::DisplayUpdateControl2, &[0xC7]),
+    Step::Cmd(Command::MasterActivation),
+];
+
+const DISPLAY_NEW_FRAME: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::DisplayUpdateControl2, &[0x0F]),
+    Step::Cmd(Command::MasterActivation),
+    Step::WaitUntilIdle,
+];
+
+// Selects the whole ram again after a window was written
+const FULL_RAM_AREA: &[Step<Command>] = &[
+    Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &FULL_RAM_X),
+    Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &FULL_RAM_Y),
+    Step::CmdWithData(Command::SetRamXAddressCounter, &[0x00]),
+    Step::CmdWithData(Command::SetRamYAddressCounter, &[0x00, 0x00]),
+];
+
+// x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+// aren't relevant
+const FULL_RAM_X: [u8; 2] = [0x00, ((WIDTH - 1) >> 3) as u8];
+// 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+const FULL_RAM_Y: [u8; 4] = [0x00, 0x00, (HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8];
+
+// Ram parameters of a window, shared by the blocking and the async driver
+struct RamWindow {
+    // X start/end positions
+    x: [u8; 2],
+    // Y start/end positions
+    y: [u8; 4],
+    // X address counter
+    x_counter: [u8; 1],
+    // Y address counter
+    y_counter: [u8; 2],
+    // Number of bytes in the window
+    len: u32,
+}
+
+impl RamWindow {
+    // None if the window is out of bounds or `x` and `width` aren't multiples of 8
+    fn new(x: u32, y: u32, width: u32, height: u32) -> Option<Self> {
+        if !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || width == 0
+            || height == 0
+            || x + width > WIDTH
+            || y + height > HEIGHT
+        {
+            return None;
+        }
+        let x_end = x + width - 1;
+        let y_end = y + height - 1;
+        Some(RamWindow {
+            // x is positioned in bytes, so the last 3 bits which show the position inside a byte
+            // in the ram aren't relevant
+            x: [(x >> 3) as u8, (x_end >> 3) as u8],
+            // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+            y: [y as u8, (y >> 8) as u8, y_end as u8, (y_end >> 8) as u8],
+            x_counter: [(x >> 3) as u8],
+            y_counter: [y as u8, (y >> 8) as u8],
+            len: width / 8 * height,
+        })
+    }
+
+    // Writes `buffer` into the window of `ram`, to be followed by FULL_RAM_AREA
+    fn write<'a>(&'a self, ram: Command, buffer: &'a [u8]) -> [Step<'a, Command>; 7] {
+        [
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &self.x),
+            Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &self.y),
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Command::SetRamXAddressCounter, &self.x_counter),
+            Step::CmdWithData(Command::SetRamYAddressCounter, &self.y_counter),
+            Step::CmdWithData(ram, buffer),
+        ]
+    }
+
+    // Fills the window in both rams with `color`, to be followed by FULL_RAM_AREA
+    fn clear(&self, color: u8) -> [Step<'_, Command>; 13] {
+        [
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Command::SetRamXAddressStartEndPosition, &self.x),
+            Step::CmdWithData(Command::SetRamYAddressStartEndPosition, &self.y),
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Command::SetRamXAddressCounter, &self.x_counter),
+            Step::CmdWithData(Command::SetRamYAddressCounter, &self.y_counter),
+            Step::Cmd(Command::WriteRam),
+            Step::DataXTimes(color, self.len),
+            Step::WaitUntilIdle,
+            Step::CmdWithData(Command::SetRamXAddressCounter, &self.x_counter),
+            Step::CmdWithData(Command::SetRamYAddressCounter, &self.y_counter),
+            Step::Cmd(Command::WriteRam2),
+            Step::DataXTimes(color, self.len),
+        ]
+    }
+}
+
 /// Display with Fullsize buffer for use with the 2in9 EPD V2
 #[cfg(feature = "graphics")]
 pub type Display2in9 = crate::graphics::Display<
@@ -99,6 +314,26 @@ pub type Display2in9 = crate::graphics::Display<
     Color,
 >;
 
+/// Full size buffer keeping the last shown frame, for quick refreshes of the 2in9 EPD V2
+#[cfg(feature = "graphics")]
+pub type DoubleBufferedDisplay2in9 = crate::double_buffer::DoubleBufferedDisplay<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Display with Fullsize buffer for use with the 2in9 EPD V2 in 4-gray mode
+#[cfg(feature = "graphics")]
+pub type Display2in9Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { 2 * buffer_len(WIDTH as usize, HEIGHT as usize) },
+    crate::color::Gray2,
+>;
+
 /// Epd2in9 driver
 ///
 pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
@@ -108,46 +343,29 @@ pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// 4-gray mode
+    grayscale: bool,
+    /// A table loaded with `set_custom_lut` replaces the one from OTP
+    custom_lut: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.reset(delay, 10_000, 2_000);
-
-        self.wait_until_idle(spi, delay)?;
-        self.interface.cmd(spi, Command::SwReset)?;
-        self.wait_until_idle(spi, delay)?;
-
-        // 3 Databytes:
-        // A[7:0]
-        // 0.. A[8]
-        // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
-        self.interface
-            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])?;
-
-        // One Databyte with default value 0x03
-        //  -> address: x increment, y increment, address counter is updated in x direction
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
-
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])?;
-
-        self.set_ram_counter(spi, delay, 0, 0)?;
-
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.grayscale = false;
+        self.custom_lut = false;
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT)
     }
 }
 
@@ -156,12 +374,27 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display2in9;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        grayscale: true,
+        quick_refresh: true,
+        custom_lut: true,
+        ..Capabilities::BASIC
+    };
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -178,13 +411,15 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
 
         let mut epd = Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            grayscale: false,
+            custom_lut: false,
         };
 
         epd.init(spi, delay)?;
@@ -192,15 +427,19 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
-        self.interface
-            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
-        Ok(())
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)?;
         Ok(())
     }
@@ -210,11 +449,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
     }
 
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -224,26 +466,35 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
-        self.wait_until_idle(spi, delay)?;
-        self.set_ram_area(spi, x, y, x + width, y + height)?;
-        self.set_ram_counter(spi, delay, x, y)?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
-        Ok(())
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.write_ram_window(spi, delay, Command::WriteRam, buffer, x, y, width, height)
     }
 
     /// actually is the "Turn on Display" sequence
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let sequence = if self.grayscale || self.custom_lut {
+            START_REFRESH_LUT_REGISTER
+        } else {
+            START_REFRESH
+        };
+        self.interface.run(spi, delay, BUSY_SIGNAL, sequence)
+    }
+
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
     }
 
     fn update_and_display_frame(
@@ -251,13 +502,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
 
         // clear the ram with the background color
@@ -278,21 +533,47 @@ where
         &self.background_color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
+        self.custom_lut = false;
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match mode {
+            RefreshMode::Full if self.grayscale => self.init(spi, delay),
+            RefreshMode::Full => self.set_lut(spi, delay, Some(RefreshLut::Full)),
+            RefreshMode::Grayscale => self.init_grayscale(spi, delay),
+            _ => Err(EpdError::Unsupported),
+        }
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle_for(spi, delay, BUSY_SIGNAL)
     }
 }
 
@@ -300,85 +581,29 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn use_full_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // choose full frame/ram
-        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
-
-        // start from the beginning
-        self.set_ram_counter(spi, delay, 0, 0)
-    }
-
-    fn set_ram_area(
-        &mut self,
-        spi: &mut SPI,
-        start_x: u32,
-        start_y: u32,
-        end_x: u32,
-        end_y: u32,
-    ) -> Result<(), SPI::Error> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
-
-        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
-        // aren't relevant
-        self.interface.cmd_with_data(
-            spi,
-            Command::SetRamXAddressStartEndPosition,
-            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
-        )?;
-
-        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
-        self.interface.cmd_with_data(
-            spi,
-            Command::SetRamYAddressStartEndPosition,
-            &[
-                start_y as u8,
-                (start_y >> 8) as u8,
-                end_y as u8,
-                (end_y >> 8) as u8,
-            ],
-        )
-    }
-
-    fn set_ram_counter(
+    // Writes `buffer` into a window of one of the rams and selects the whole ram again
+    #[allow(clippy::too_many_arguments)]
+    fn write_ram_window(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
+        ram: Command,
+        buffer: &[u8],
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
-        // aren't relevant
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[x as u8])?;
-
-        // 2 Databytes: A[7:0] & 0..A[8]
-        self.interface.cmd_with_data(
-            spi,
-            Command::SetRamYAddressCounter,
-            &[y as u8, (y >> 8) as u8],
-        )?;
-        Ok(())
-    }
-
-    /// Set your own LUT, this function is also used internally for set_lut
-    fn set_lut_helper(
-        &mut self,
-        spi: &mut SPI,
-        delay: &mut DELAY,
-        buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
         self.interface
-            .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+            .run(spi, delay, BUSY_SIGNAL, &window.write(ram, buffer))?;
+        self.interface.run(spi, delay, BUSY_SIGNAL, FULL_RAM_AREA)
     }
 }
 
@@ -387,9 +612,9 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// To be followed immediately by `update_new_frame`.
@@ -398,7 +623,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.interface
             .cmd_with_data(spi, Command::WriteRam2, buffer)
@@ -410,25 +636,10 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.interface.reset(delay, 10_000, 2_000);
-
-        self.set_lut_helper(spi, delay, &LUT_PARTIAL_2IN9)?;
-        self.interface.cmd_with_data(
-            spi,
-            Command::WriteOtpSelection,
-            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
-        )?;
-        self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-
-        self.wait_until_idle(spi, delay)?;
-
-        self.use_full_frame(spi, delay)?;
+            .run(spi, delay, BUSY_SIGNAL, PREPARE_NEW_FRAME)?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)?;
@@ -436,13 +647,13 @@ where
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
-        self.interface.cmd(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+            .run(spi, delay, BUSY_SIGNAL, DISPLAY_NEW_FRAME)
     }
 
     /// Updates and displays the new frame.
@@ -451,14 +662,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_new_frame(spi, delay)?;
         Ok(())
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned. To be followed by `update_partial_new_frame` for the same window.
     fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
@@ -468,13 +679,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.write_ram_window(spi, delay, Command::WriteRam2, buffer, x, y, width, height)
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Loads the partial LUT like `update_new_frame`, so the window is shown with
+    /// `display_new_frame`.
     fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
@@ -484,13 +694,16 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, PREPARE_NEW_FRAME)?;
+
+        self.write_ram_window(spi, delay, Command::WriteRam, buffer, x, y, width, height)
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Clears the window in both rams with the background color
     fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -499,9 +712,120 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        //TODO supported by display?
-        unimplemented!()
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = RamWindow::new(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.background_color.get_byte_value();
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, &window.clear(color))?;
+        self.interface.run(spi, delay, BUSY_SIGNAL, FULL_RAM_AREA)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn init_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT_4GRAY)?;
+        self.grayscale = true;
+        Ok(())
+    }
+
+    fn update_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let plane_len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        check_buffer_len(buffer, 2 * plane_len)?;
+        self.wait_until_idle(spi, delay)?;
+
+        let (high, low) = buffer.split_at(plane_len);
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+        self.interface.cmd_with_data(spi, Command::WriteRam, high)?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+        self.interface.cmd_with_data(spi, Command::WriteRam2, low)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareCustomLut<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    type Lut = Ssd1680Lut;
+
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &Ssd1680Lut,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &lut.waveform_bytes())?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &[lut.end_option])?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &[lut.voltages.gate()])?;
+        self.interface
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &lut.voltages.source())?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[lut.voltages.vcom()])?;
+        self.custom_lut = true;
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.interface.cmd(spi, Command::WriteRam)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
     }
 }
 
@@ -515,4 +839,56 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd2in9<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn quick_refresh_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        // a 16x3 window at (16, 10), with different rows in the old and the new frame
+        let old = [0x11, 0x12, 0x21, 0x22, 0x31, 0x32];
+        let new = [0xA1, 0xA2, 0xB1, 0xB2, 0xC1, 0xC2];
+        epd.update_partial_old_frame(&mut spi, &mut SimDelay, &old, 16, 10, 16, 3)
+            .unwrap();
+        epd.update_partial_new_frame(&mut spi, &mut SimDelay, &new, 16, 10, 16, 3)
+            .unwrap();
+        epd.display_new_frame(&mut spi, &mut SimDelay).unwrap();
+
+        let line = WIDTH as usize / 8;
+        for (plane, rows) in [(0, new), (1, old)] {
+            let ram = sim.ram(plane);
+            for (row, data) in rows.chunks_exact(2).enumerate() {
+                let start = (10 + row) * line + 2;
+                assert_eq!(ram[start..start + 2], *data);
+                assert_eq!(ram[start - 1], 0x00);
+                assert_eq!(ram[start + 2], 0x00);
+            }
+            assert_eq!(ram[9 * line + 2..9 * line + 4], [0x00, 0x00]);
+            assert_eq!(ram[13 * line + 2..13 * line + 4], [0x00, 0x00]);
+        }
+        assert_eq!(sim.shown(0), sim.ram(0));
+
+        assert_eq!(
+            epd.update_partial_new_frame(&mut spi, &mut SimDelay, &new, 12, 10, 16, 3),
+            Err(EpdError::InvalidWindow)
+        );
+    }
 }
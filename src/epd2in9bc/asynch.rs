@@ -0,0 +1,358 @@
+//! Async driver for the Waveshare 2.9" (B/C) E-Ink Display
+//!
+//! Uses the same command sequences as the blocking [`Epd2in9bc`](super::Epd2in9bc).
+
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::command::Command;
+use super::{
+    partial_window, BLACK_BORDER, BUSY_SIGNAL, CHROMATIC_BORDER, CLEAR_FRAME,
+    DEFAULT_BACKGROUND_COLOR, HEIGHT, INIT, NUM_DISPLAY_BITS, SLEEP, START_REFRESH,
+    VCOM_DATA_INTERVAL, WHITE_BORDER, WIDTH,
+};
+use crate::async_interface::AsyncDisplayInterface;
+use crate::async_traits::{AsyncWaveshareDisplay, AsyncWaveshareThreeColorDisplay};
+use crate::buffer_len;
+use crate::color::{Color, TriColor};
+use crate::error::{check_buffer_len, EpdError};
+use crate::traits::RefreshLut;
+
+/// Async Epd2in9bc driver
+pub struct Epd2in9bc<SPI, BUSY, DC, RST, DELAY> {
+    interface: AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY>,
+    color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.update_achromatic_frame(spi, delay, black).await?;
+        self.update_chromatic_frame(spi, delay, chromatic).await
+    }
+
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission1, black)
+            .await
+    }
+
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, chromatic)
+            .await?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+
+    async fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            black,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await
+    }
+
+    async fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission2,
+            chromatic,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, EpdError<SPI::Error, BUSY::Error>> {
+        let interface = AsyncDisplayInterface::new(busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd2in9bc { interface, color };
+
+        epd.wake_up(spi, delay).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP).await
+    }
+
+    async fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn set_busy_timeout(&mut self, max_polls: Option<u32>) {
+        self.interface.set_busy_timeout(max_polls);
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+
+        // Clear the chromatic layer
+        let color = self.color.get_byte_value();
+
+        self.interface
+            .cmd(spi, Command::DataStartTransmission2)
+            .await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await?;
+
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await?;
+
+        // Clear the chromatic layer of the window
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.color.get_byte_value();
+        self.interface.cmd(spi, Command::PartialIn).await?;
+        self.interface
+            .cmd_with_data(spi, Command::PartialWindow, &window)
+            .await?;
+        self.interface
+            .cmd(spi, Command::DataStartTransmission2)
+            .await?;
+        self.interface
+            .data_x_times(spi, color, width / 8 * height)
+            .await?;
+        self.interface.cmd(spi, Command::PartialOut).await?;
+
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, START_REFRESH)
+            .await?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_frame(spi, buffer, delay).await?;
+        self.display_frame(spi, delay).await
+    }
+
+    async fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, CLEAR_FRAME)
+            .await
+    }
+
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        Ok(())
+    }
+
+    async fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    // Writes one layer of a window
+    #[allow(clippy::too_many_arguments)]
+    async fn update_partial_layer(
+        &mut self,
+        spi: &mut SPI,
+        layer: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+
+        self.interface.cmd(spi, Command::PartialIn).await?;
+        self.interface
+            .cmd_with_data(spi, Command::PartialWindow, &window)
+            .await?;
+        self.interface.cmd_with_data(spi, layer, buffer).await?;
+        self.interface.cmd(spi, Command::PartialOut).await
+    }
+
+    /// Set the outer border of the display to the chosen color.
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        let border = match color {
+            TriColor::Black => BLACK_BORDER,
+            TriColor::White => WHITE_BORDER,
+            TriColor::Chromatic => CHROMATIC_BORDER,
+        };
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::VcomAndDataIntervalSetting,
+                &[border | VCOM_DATA_INTERVAL],
+            )
+            .await
+    }
+}
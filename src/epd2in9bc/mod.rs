@@ -4,10 +4,8 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
-//!use embedded_graphics::{
-//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
-//!};
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
 //!use epd_waveshare::{epd2in9bc::*, prelude::*};
 //!#
 //!# let expectations = [];
@@ -23,29 +21,25 @@
 //!let mut epd = Epd2in9bc::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
-//!// This display is for the black/white pixels
-//!let mut mono_display = Display2in9bc::default();
+//!// This display is for the black/white/chromatic pixels
+//!let mut tricolor_display = Display2in9bc::default();
 //!
-//!// Use embedded graphics for drawing
-//!// A black line
+//!// Use embedded graphics for drawing a black line
 //!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
-//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-//!    .draw(&mut mono_display);
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+//!    .draw(&mut tricolor_display);
 //!
-//!// Use a second display for red/yellow
-//!let mut chromatic_display = Display2in9bc::default();
-//!
-//!// We use `Black` but it will be shown as red/yellow
+//!// We use `chromatic` but it will be shown as red/yellow
 //!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
-//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-//!    .draw(&mut chromatic_display);
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut tricolor_display);
 //!
 //!// Display updated frame
 //!epd.update_color_frame(
 //!    &mut spi,
 //!    &mut delay,
-//!    &mono_display.buffer(),
-//!    &chromatic_display.buffer()
+//!    &tricolor_display.bw_buffer(),
+//!    &tricolor_display.chromatic_buffer()
 //!)?;
 //!epd.display_frame(&mut spi, &mut delay)?;
 //!
@@ -59,9 +53,12 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
+use crate::sequence::{Busy, Step};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    Capabilities, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareStreamingDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in9bc in pixels
@@ -80,21 +77,102 @@ const BLACK_BORDER: u8 = 0x30;
 const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 
+const BUSY_SIGNAL: Busy<Command> = Busy {
+    is_busy_low: IS_BUSY_LOW,
+    status_command: None,
+};
+
+// Values taken from datasheet and sample code
+const INIT: &[Step<Command>] = &[
+    Step::Reset(10_000, 10_000),
+    // start the booster
+    Step::CmdWithData(Command::BoosterSoftStart, &[0x17, 0x17, 0x17]),
+    // power on
+    Step::Cmd(Command::PowerOn),
+    Step::Delay(5000),
+    Step::WaitUntilIdle,
+    // set the panel settings
+    Step::CmdWithData(Command::PanelSetting, &[0x8F]),
+    Step::CmdWithData(
+        Command::VcomAndDataIntervalSetting,
+        &[WHITE_BORDER | VCOM_DATA_INTERVAL],
+    ),
+    // set resolution
+    Step::CmdWithData(Command::ResolutionSetting, &RESOLUTION),
+    Step::CmdWithData(Command::VcmDcSetting, &[0x0A]),
+    Step::WaitUntilIdle,
+];
+
+// Section 8.2 from datasheet
+const SLEEP: &[Step<Command>] = &[
+    Step::CmdWithData(
+        Command::VcomAndDataIntervalSetting,
+        &[FLOATING_BORDER | VCOM_DATA_INTERVAL],
+    ),
+    Step::Cmd(Command::PowerOff),
+    // The example STM code from Github has a wait after PowerOff
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::DeepSleep, &[0xA5]),
+];
+
+const START_REFRESH: &[Step<Command>] = &[Step::Cmd(Command::DisplayRefresh)];
+
+const CLEAR_FRAME: &[Step<Command>] = &[
+    Step::CmdWithData(Command::ResolutionSetting, &RESOLUTION),
+    // Clear the black
+    Step::Cmd(Command::DataStartTransmission1),
+    Step::DataXTimes(DEFAULT_BACKGROUND_COLOR.get_byte_value(), NUM_DISPLAY_BITS),
+    // Clear the chromatic
+    Step::Cmd(Command::DataStartTransmission2),
+    Step::DataXTimes(DEFAULT_BACKGROUND_COLOR.get_byte_value(), NUM_DISPLAY_BITS),
+    Step::WaitUntilIdle,
+];
+
+const RESOLUTION: [u8; 3] = [WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8];
+
+// Parameters of `PartialWindow`, or None if the window is out of bounds or `x` and `width` aren't
+// multiples of 8
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> Option<[u8; 7]> {
+    if !x.is_multiple_of(8)
+        || !width.is_multiple_of(8)
+        || width == 0
+        || height == 0
+        || x + width > WIDTH
+        || y + height > HEIGHT
+    {
+        return None;
+    }
+    let x_end = x + width - 1;
+    let y_end = y + height - 1;
+    Some([
+        x as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        // gates scan both inside and outside of the window
+        0x01,
+    ])
+}
+
 use crate::color::{Color, TriColor};
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
+#[cfg(feature = "async")]
+pub mod asynch;
+
 /// Full size buffer for use with the 2in9b/c EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
 #[cfg(feature = "graphics")]
 pub type Display2in9bc = crate::graphics::Display<
     WIDTH,
     HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    true,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd2in9bc driver
@@ -108,42 +186,17 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // Values taken from datasheet and sample code
-
-        self.interface.reset(delay, 10_000, 10_000);
-
-        // start the booster
-        self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
-
-        // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_us(5000);
-        self.wait_until_idle(spi, delay)?;
-
-        // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x8F])?;
-
-        self.cmd_with_data(
-            spi,
-            Command::VcomAndDataIntervalSetting,
-            &[WHITE_BORDER | VCOM_DATA_INTERVAL],
-        )?;
-
-        // set resolution
-        self.send_resolution(spi)?;
-
-        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0A])?;
-
-        self.wait_until_idle(spi, delay)?;
-
-        Ok(())
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT)
     }
 }
 
@@ -152,9 +205,9 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, CS, BUSY, DC
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     fn update_color_frame(
@@ -163,7 +216,9 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.update_achromatic_frame(spi, delay, black)?;
         self.update_chromatic_frame(spi, delay, chromatic)
     }
@@ -176,7 +231,8 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(black, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface.data(spi, black)?;
         Ok(())
@@ -190,13 +246,59 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(chromatic, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data(spi, chromatic)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
+
+    fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            black,
+            x,
+            y,
+            width,
+            height,
+        )
+    }
+
+    fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission2,
+            chromatic,
+            x,
+            y,
+            width,
+            height,
+        )?;
+
+        self.wait_until_idle(spi, delay)?;
+        Ok(())
+    }
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
@@ -204,12 +306,25 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display2in9bc;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize * 2);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        chromatic: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -218,7 +333,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -229,24 +344,19 @@ where
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // Section 8.2 from datasheet
-        self.interface.cmd_with_data(
-            spi,
-            Command::VcomAndDataIntervalSetting,
-            &[FLOATING_BORDER | VCOM_DATA_INTERVAL],
-        )?;
-
-        self.command(spi, Command::PowerOff)?;
-        // The example STM code from Github has a wait after PowerOff
-        self.wait_until_idle(spi, delay)?;
-
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-
-        Ok(())
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
@@ -258,6 +368,14 @@ where
         &self.color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -271,7 +389,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         self.interface.data(spi, buffer)?;
@@ -286,7 +405,8 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned. The chromatic layer of the window is cleared.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -296,44 +416,69 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        Ok(())
-    }
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_layer(
+            spi,
+            Command::DataStartTransmission1,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )?;
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.command(spi, Command::DisplayRefresh)?;
+        // Clear the chromatic layer of the window
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        let color = self.color.get_byte_value();
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color, width / 8 * height)?;
+        self.interface.cmd(spi, Command::PartialOut)?;
 
         self.wait_until_idle(spi, delay)?;
         Ok(())
     }
 
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, START_REFRESH)
+    }
+
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.send_resolution(spi)?;
-
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
-        // Clear the black
-        self.interface.cmd(spi, Command::DataStartTransmission1)?;
-
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
-
-        // Clear the chromatic
-        self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
-
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, CLEAR_FRAME)
     }
 
     fn set_lut(
@@ -341,13 +486,16 @@ where
         _spi: &mut SPI,
         _delay: &mut DELAY,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
-        Ok(())
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle_for(spi, delay, BUSY_SIGNAL)
     }
 }
 
@@ -355,41 +503,47 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9bc<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
-        self.interface.cmd(spi, command)
-    }
-
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
-    }
-
     fn cmd_with_data(
         &mut self,
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
-        let w = self.width();
-        let h = self.height();
-
-        self.command(spi, Command::ResolutionSetting)?;
-
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+    // Writes one layer of a window
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_layer(
+        &mut self,
+        spi: &mut SPI,
+        layer: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+
+        self.interface.cmd(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.cmd_with_data(spi, layer, buffer)?;
+        self.interface.cmd(spi, Command::PartialOut)
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let border = match color {
             TriColor::Black => BLACK_BORDER,
             TriColor::White => WHITE_BORDER,
@@ -402,3 +556,111 @@ where
         )
     }
 }
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.cmd(spi, Command::DataStartTransmission1)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn end_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // Clear the chromatic layer
+        let color = self.color.get_byte_value();
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+
+        self.wait_until_idle(spi, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd2in9bc<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_color_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in9bc::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        // a 16x3 window at (8, 4) with a chromatic first row
+        let black = [0xFF; 2 * 3];
+        let chromatic = [0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+        epd.update_partial_color_frame(&mut spi, &mut SimDelay, &black, &chromatic, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(!sim.shown_pixel(0, 7, 4));
+        assert!(!sim.shown_pixel(0, 24, 4));
+        assert!(!sim.shown_pixel(0, 8, 3));
+        assert!(!sim.shown_pixel(0, 8, 7));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 4));
+        assert!(!sim.shown_pixel(1, 8, 5));
+        assert!(!sim.shown_pixel(1, 7, 4));
+        assert!(!sim.shown_pixel(1, 24, 4));
+
+        // only the black window, the chromatic one is cleared (all bits set)
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &black, 8, 4, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 8, 4));
+        assert!(sim.shown_pixel(0, 23, 6));
+        assert!(sim.shown_pixel(1, 8, 4));
+        assert!(sim.shown_pixel(1, 23, 6));
+        assert!(!sim.shown_pixel(1, 24, 4));
+    }
+}
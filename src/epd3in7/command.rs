@@ -9,7 +9,7 @@ use crate::traits;
 /// For more infos about the addresses and what they are doing look into the pdfs
 ///
 /// The description of the single commands is mostly taken from EDP3IN7 specification
-#[allow(dead_code)]
+#[allow(dead_code, clippy::empty_docs)]
 #[derive(Copy, Clone)]
 pub(crate) enum Command {
     ///
@@ -53,6 +53,8 @@ pub(crate) enum Command {
     DisplayUpdateSequenceSetting = 0x22,
     /// This command will transfer its data to B/W RAM, until another command is written
     WriteRam = 0x24,
+    /// This command will transfer its data to RED RAM, until another command is written
+    WriteRam2 = 0x26,
     /// This command writes VCOM register from MCU interface
     WriteVcomRegister = 0x2C,
     /// This command writes LUT register from MCU interface (105 bytes),
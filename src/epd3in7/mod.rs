@@ -15,8 +15,12 @@ use self::constants::*;
 
 use crate::buffer_len;
 use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    Capabilities, InternalWiAdditions, RefreshLut, RefreshMode, WaveshareDisplay,
+    WaveshareGrayscaleDisplay, WaveshareStreamingDisplay,
+};
 
 /// Width of the display.
 pub const WIDTH: u32 = 280;
@@ -39,12 +43,24 @@ pub type Display3in7 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with Fullsize buffer for use with the 3in7 EPD in 4-gray mode
+#[cfg(feature = "graphics")]
+pub type Display3in7Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { 2 * buffer_len(WIDTH as usize, HEIGHT as usize) },
+    crate::color::Gray2,
+>;
+
 /// EPD3in7 driver
 pub struct EPD3in7<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
     background_color: Color,
+    /// 4-gray mode
+    grayscale: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -52,24 +68,30 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.grayscale = false;
+
         // reset the device
-        self.interface.reset(delay, 30, 10);
+        self.interface.reset(delay, 30, 10)?;
 
         self.interface.cmd(spi, Command::SwReset)?;
         delay.delay_us(300000u32);
 
         self.interface
             .cmd_with_data(spi, Command::AutoWriteRedRamRegularPattern, &[0xF7])?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         self.interface
             .cmd_with_data(spi, Command::AutoWriteBwRamRegularPattern, &[0xF7])?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
 
         self.interface
             .cmd_with_data(spi, Command::GateSetting, &[0xDF, 0x01, 0x00])?;
@@ -126,13 +148,26 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
 
+    #[cfg(feature = "graphics")]
+    type Display = Display3in7;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        grayscale: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -141,21 +176,30 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let mut epd = EPD3in7 {
             interface: DisplayInterface::new(cs, busy, dc, rst, delay_us),
             background_color: DEFAULT_BACKGROUND_COLOR,
+            grayscale: false,
         };
 
         epd.init(spi, delay)?;
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, Command::Sleep, &[0xF7])?;
         self.interface.cmd(spi, Command::PowerOff)?;
         self.interface
@@ -171,6 +215,14 @@ where
         &self.background_color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -184,8 +236,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.interface
             .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
         self.interface
@@ -197,40 +249,92 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned. Select [`RefreshLut::Quick`] to refresh only the pixels that changed.
+    ///
+    /// Not supported in 4-gray mode.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
-        delay: &mut DELAY,
+        _delay: &mut DELAY,
         buffer: &[u8],
         x: u32,
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
-        todo!()
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        if self.grayscale {
+            return Err(EpdError::Unsupported);
+        }
+        if !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || width == 0
+            || height == 0
+            || x + width > WIDTH
+            || y + height > HEIGHT
+        {
+            return Err(EpdError::InvalidWindow);
+        }
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+
+        // back to the whole RAM for the full frame updates
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         //self.interface
         //    .cmd_with_data(spi, Command::WRITE_LUT_REGISTER, &LUT_1GRAY_GC)?;
         self.interface.cmd(spi, Command::DisplayUpdateSequence)?;
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface
             .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
         self.interface
@@ -248,7 +352,7 @@ where
         spi: &mut SPI,
         _delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let buffer = match refresh_rate {
             Some(RefreshLut::Full) | None => &LUT_1GRAY_GC,
             Some(RefreshLut::Quick) => &LUT_1GRAY_DU,
@@ -259,8 +363,272 @@ where
         Ok(())
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let refresh_lut = match mode {
+            RefreshMode::Full => RefreshLut::Full,
+            RefreshMode::Partial => RefreshLut::Quick,
+            RefreshMode::Grayscale => return self.init_grayscale(spi, delay),
+            RefreshMode::FastFull => return Err(EpdError::Unsupported),
+        };
+        if self.grayscale {
+            self.init(spi, delay)?;
+        }
+        self.set_lut(spi, delay, Some(refresh_lut))
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> EPD3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    // x is positioned in pixels on this controller
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for EPD3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn init_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // the black and white init with a different border waveform, display option and
+        // update sequence
+        self.interface.reset(delay, 30, 10)?;
+
+        self.interface.cmd(spi, Command::SwReset)?;
+        delay.delay_us(300000u32);
+
+        self.interface
+            .cmd_with_data(spi, Command::AutoWriteRedRamRegularPattern, &[0xF7])?;
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+        self.interface
+            .cmd_with_data(spi, Command::AutoWriteBwRamRegularPattern, &[0xF7])?;
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::GateSetting, &[0xDF, 0x01, 0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::GateVoltage, &[0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::GateVoltageSource, &[0x41, 0xA8, 0x32])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::DataEntrySequence, &[0x03])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x00])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0xC0],
+        )?;
+
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[0x44])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::DisplayOption, &[0x00; 10])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[0x00, 0x00, 0x17, 0x01],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[0x00, 0x00, 0xDF, 0x01],
+        )?;
+
+        // display with the LUT from the register instead of the OTP
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateSequenceSetting, &[0xC7])?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &LUT_4GRAY_GC)?;
+        self.grayscale = true;
+        Ok(())
+    }
+
+    fn update_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let plane_len = buffer_len(WIDTH as usize, HEIGHT as usize);
+        check_buffer_len(buffer, 2 * plane_len)?;
+
+        let (high, low) = buffer.split_at(plane_len);
+        for (command, plane) in [(Command::WriteRam, high), (Command::WriteRam2, low)] {
+            self.interface
+                .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
+            self.interface
+                .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+            self.interface.cmd_with_data(spi, command, plane)?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for EPD3in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])?;
+        self.interface
+            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])?;
+
+        self.interface.cmd(spi, Command::WriteRam)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 280);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<EPD3in7<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = EPD3in7::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        // a 16x3 window at (264, 477), in the bottom right corner
+        let buffer = [0xFF; 2 * 3];
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &buffer, 264, 477, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(0, 264, 477));
+        assert!(sim.shown_pixel(0, 279, 479));
+        assert!(!sim.shown_pixel(0, 263, 477));
+        assert!(!sim.shown_pixel(0, 264, 476));
+
+        // windows past the edges or not aligned to bytes are rejected
+        for (x, y, width, height) in [
+            (272, 0, 16, 1),
+            (0, 478, 8, 3),
+            (4, 0, 8, 1),
+            (0, 0, 12, 1),
+            (0, 0, 0, 1),
+            (0, 0, 8, 0),
+        ] {
+            assert_eq!(
+                epd.update_partial_frame(&mut spi, &mut SimDelay, &buffer, x, y, width, height),
+                Err(EpdError::InvalidWindow)
+            );
+        }
+
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Grayscale)
+            .unwrap();
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut SimDelay, &buffer, 0, 0, 16, 3),
+            Err(EpdError::Unsupported)
+        );
+    }
+}
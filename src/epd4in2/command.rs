@@ -87,6 +87,11 @@ pub(crate) enum Command {
     ///
     /// from IL0373
     LutBlackToBlack = 0x24,
+    /// This command stores the Border Look-Up Table, which is used as a fifth gray level
+    /// transition table in 4-gray mode.
+    ///
+    /// from IL0373
+    LutBorder = 0x25,
     /// The command controls the PLL clock frequency.
     PllControl = 0x30,
     /// This command reads the temperature sensed by the temperature sensor.
@@ -125,6 +125,12 @@ pub(crate) enum Command {
     ReadVcomValue = 0x81,
     /// This command sets `VCOM_DC` value.
     VcmDcSetting = 0x82,
+    /// Sets window size for the partial update
+    PartialWindow = 0x90,
+    /// Sets chip into partial update mode
+    PartialIn = 0x91,
+    /// Quits partial update mode
+    PartialOut = 0x92,
     // /// This is in all the Waveshare controllers for EPD6in65f, but it's not documented
     // /// anywhere in the datasheet `¯\_(ツ)_/¯`
     FlashMode = 0xE3,
@@ -15,8 +15,12 @@ use embedded_hal::{
 };
 
 use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    Capabilities, InternalWiAdditions, RefreshLut, RefreshMode, WaveshareDisplay,
+    WaveshareStreamingDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -39,6 +43,8 @@ pub const HEIGHT: u32 = 528;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White; // Inverted for HD as compared to 7in5 v2 (HD: 0xFF = White)
 const IS_BUSY_LOW: bool = false;
+/// Gate lines set with `DriverOutputControl`, the RAM has one row for each
+const RAM_ROWS: u32 = 0x2AF + 1;
 
 /// EPD7in5 (HD) driver
 ///
@@ -47,6 +53,8 @@ pub struct Epd7in5<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
     color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -54,14 +62,18 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // Reset the device
-        self.interface.reset(delay, 10_000, 2_000);
+        self.interface.reset(delay, 10_000, 2_000)?;
 
         // HD procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in5_HD.py
@@ -107,12 +119,25 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display7in5;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -121,22 +146,34 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd7in5 { interface, color };
+        let mut epd = Epd7in5 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+        };
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0x01])?;
         Ok(())
@@ -147,45 +184,108 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
         self.cmd_with_data(spi, Command::WriteRamBw, buffer)?;
-        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.cmd_with_data(
+            spi,
+            Command::DisplayUpdateControl2,
+            &[self.update_control()],
+        )?;
         Ok(())
     }
 
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned.
+    ///
+    /// With [`RefreshLut::Quick`] only the pixels that changed are refreshed.
     fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        if !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || width == 0
+            || height == 0
+            || x + width > WIDTH
+            || y + height > HEIGHT
+        {
+            return Err(EpdError::InvalidWindow);
+        }
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.wait_until_idle(spi, delay)?;
+
+        // The RAM rows are written backwards, and `update_frame` starts at RAM row 0 before
+        // wrapping around to the last one. Row `y` of a frame is in RAM row
+        // `(RAM_ROWS - y) % RAM_ROWS`.
+        let (mut y, mut buffer) = (y, buffer);
+        if y == 0 {
+            let (row, rest) = buffer.split_at(width as usize / 8);
+            self.write_ram_window(spi, x, width, 0, 0, row)?;
+            (y, buffer) = (1, rest);
+        }
+        if !buffer.is_empty() {
+            let rows = (buffer.len() / (width as usize / 8)) as u32;
+            let ram_start = RAM_ROWS - y;
+            self.write_ram_window(spi, x, width, ram_start, ram_start - rows + 1, buffer)?;
+        }
+
+        self.cmd_with_data(spi, Command::SetRamXStartEnd, &[0x00, 0x00, 0x6F, 0x03])?;
+        self.cmd_with_data(spi, Command::SetRamYStartEnd, &[0xAF, 0x02, 0x00, 0x00])?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::DisplayUpdateControl2,
+            &[self.update_control()],
+        )
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)?;
+        self.finish_refresh(spi, delay)
+    }
+
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle(spi, delay)?;
         Ok(())
     }
 
+    fn is_busy(&mut self, _spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+
     fn update_and_display_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         let pixel_count = WIDTH * HEIGHT / 8;
         let background_color_byte = self.color.get_byte_value();
 
@@ -212,6 +312,14 @@ where
         &self.color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -220,17 +328,53 @@ where
         HEIGHT
     }
 
+    /// [`RefreshLut::Quick`] only refreshes the pixels that changed. Going back to
+    /// [`RefreshLut::Full`] re-initializes the display.
     fn set_lut(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match refresh_rate {
+            Some(RefreshLut::Quick) if self.refresh == RefreshLut::Full => {
+                self.wait_until_idle(spi, delay)?;
+                // RAM ping-pong, so the controller keeps the shown frame to compare with
+                self.cmd_with_data(
+                    spi,
+                    Command::DisplayOptionWrite,
+                    &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+                )?;
+                self.refresh = RefreshLut::Quick;
+                Ok(())
+            }
+            Some(RefreshLut::Full) if self.refresh == RefreshLut::Quick => {
+                self.refresh = RefreshLut::Full;
+                self.init(spi, delay)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match mode {
+            RefreshMode::Full => self.set_lut(spi, delay, Some(RefreshLut::Full)),
+            RefreshMode::Partial => self.set_lut(spi, delay, Some(RefreshLut::Quick)),
+            _ => Err(EpdError::Unsupported),
+        }
     }
 
-    fn wait_until_idle(&mut self, _spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface.wait_until_idle(delay, IS_BUSY_LOW);
+    fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle(delay, IS_BUSY_LOW)?;
         Ok(())
     }
 }
@@ -239,12 +383,16 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -253,9 +401,94 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
+
+    // Display mode 1 refreshes every pixel, display mode 2 only the ones that changed
+    fn update_control(&self) -> u8 {
+        match self.refresh {
+            RefreshLut::Full => 0xF7,
+            RefreshLut::Quick => 0xFF,
+        }
+    }
+
+    // Writes `data` into the RAM rows `ram_start` down to `ram_end`
+    fn write_ram_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        width: u32,
+        ram_start: u32,
+        ram_end: u32,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let x_end = x + width - 1;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[x as u8, (x >> 8) as u8, x_end as u8, (x_end >> 8) as u8],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYStartEnd,
+            &[
+                ram_start as u8,
+                (ram_start >> 8) as u8,
+                ram_end as u8,
+                (ram_end >> 8) as u8,
+            ],
+        )?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[x as u8, (x >> 8) as u8])?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAc,
+            &[ram_start as u8, (ram_start >> 8) as u8],
+        )?;
+        self.cmd_with_data(spi, Command::WriteRamBw, data)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
+        self.command(spi, Command::WriteRamBw)
+    }
+
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn end_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.cmd_with_data(
+            spi,
+            Command::DisplayUpdateControl2,
+            &[self.update_control()],
+        )
+    }
 }
 
 #[cfg(test)]
@@ -268,4 +501,102 @@ mod tests {
         assert_eq!(HEIGHT, 528);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd7in5<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_window_matches_full_frame() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use std::vec;
+
+        // the RAM has a row for every gate line, not only for the shown ones
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, RAM_ROWS);
+        let mut spi = sim.spi();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let empty = vec![0x00; buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        for (x, y, width, height) in [
+            (16, 10, 24, 3),
+            (0, 0, 16, 2),
+            (WIDTH - 8, HEIGHT - 1, 8, 1),
+        ] {
+            let mut frame = empty.clone();
+            for row in y..y + height {
+                let start = (row * WIDTH + x) as usize / 8;
+                frame[start..start + width as usize / 8].fill(0xA5);
+            }
+            epd.update_frame(&mut spi, &frame, &mut SimDelay).unwrap();
+            let expected = sim.ram(0);
+
+            epd.update_frame(&mut spi, &empty, &mut SimDelay).unwrap();
+            let window = vec![0xA5; (width / 8 * height) as usize];
+            epd.update_partial_frame(&mut spi, &mut SimDelay, &window, x, y, width, height)
+                .unwrap();
+            assert_eq!(sim.ram(0), expected);
+        }
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn quick_and_partial_modes() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, RAM_ROWS);
+        let mut spi = sim.spi();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let window = [0xFF; 2];
+
+        // display mode 1 refreshes every pixel
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &window, 8, 8, 16, 1)
+            .unwrap();
+        assert_eq!(sim.params(0x22).as_deref(), Some(&[0xF7][..]));
+
+        // RAM ping-pong and display mode 2 only refresh the pixels that changed
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Partial)
+            .unwrap();
+        assert_eq!(
+            sim.params(0x37).as_deref(),
+            Some(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00][..])
+        );
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &window, 8, 8, 16, 1)
+            .unwrap();
+        assert_eq!(sim.params(0x22).as_deref(), Some(&[0xFF][..]));
+
+        // going back to full refreshes initializes the controller again
+        let resets = sim.resets();
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Full)
+            .unwrap();
+        assert_eq!(sim.resets(), resets + 1);
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &window, 8, 8, 16, 1)
+            .unwrap();
+        assert_eq!(sim.params(0x22).as_deref(), Some(&[0xF7][..]));
+
+        assert_eq!(
+            epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::FastFull),
+            Err(EpdError::Unsupported)
+        );
+    }
 }
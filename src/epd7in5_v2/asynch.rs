@@ -0,0 +1,189 @@
+//! Async driver for the Waveshare 7.5" E-Ink Display (V2)
+//!
+//! Uses the same command sequences as the blocking [`Epd7in5`](super::Epd7in5).
+
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+use super::command::Command;
+use super::{
+    partial_window, BUSY_SIGNAL, CLEAR_FRAME, DEFAULT_BACKGROUND_COLOR, HEIGHT, INIT,
+    SET_FAST_FULL, SET_FULL, SLEEP, START_REFRESH, WIDTH,
+};
+use crate::async_interface::AsyncDisplayInterface;
+use crate::async_traits::AsyncWaveshareDisplay;
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
+use crate::traits::RefreshLut;
+
+/// Async Epd7in5 (V2) driver
+pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: AsyncDisplayInterface<SPI, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> AsyncWaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: Wait + InputPin,
+    DC: OutputPin<Error = BUSY::Error>,
+    RST: OutputPin<Error = BUSY::Error>,
+    DELAY: DelayNs,
+{
+    type DisplayColor = Color;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, EpdError<SPI::Error, BUSY::Error>> {
+        let interface = AsyncDisplayInterface::new(busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd7in5 { interface, color };
+
+        epd.wake_up(spi, delay).await?;
+
+        Ok(epd)
+    }
+
+    async fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT).await
+    }
+
+    async fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP).await
+    }
+
+    async fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await?;
+
+        self.interface.cmd(spi, Command::PartialIn).await?;
+        self.interface
+            .cmd_with_data(spi, Command::PartialWindow, &window)
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.interface.cmd(spi, Command::PartialOut).await
+    }
+
+    async fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, START_REFRESH)
+            .await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.update_frame(spi, buffer, delay).await?;
+        self.interface.cmd(spi, Command::DisplayRefresh).await
+    }
+
+    async fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .run(spi, delay, BUSY_SIGNAL, CLEAR_FRAME)
+            .await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn set_busy_timeout(&mut self, max_polls: Option<u32>) {
+        self.interface.set_busy_timeout(max_polls);
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        match refresh_rate {
+            Some(RefreshLut::Full) => self.interface.run(spi, delay, BUSY_SIGNAL, SET_FULL).await,
+            Some(RefreshLut::Quick) => {
+                self.interface
+                    .run(spi, delay, BUSY_SIGNAL, SET_FAST_FULL)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, BUSY::Error>> {
+        self.interface
+            .wait_until_idle(spi, delay, BUSY_SIGNAL)
+            .await
+    }
+}
@@ -128,9 +128,17 @@ pub(crate) enum Command {
     ReadVcomValue = 0x81,
     /// This command sets `VCOM_DC` value.
     VcmDcSetting = 0x82,
-    // /// This is in all the Waveshare controllers for Epd7in5, but it's not documented
-    // /// anywhere in the datasheet `¯\_(ツ)_/¯`
-    // FlashMode = 0xE5,
+    /// Sets window size for the partial update
+    PartialWindow = 0x90,
+    /// Sets chip into partial update mode
+    PartialIn = 0x91,
+    /// Quits partial update mode
+    PartialOut = 0x92,
+    /// Makes the controller use the temperature set with `ForceTemperature` instead of the
+    /// measured one
+    CascadeSetting = 0xE0,
+    /// Sets the temperature the built-in waveform is picked for
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
@@ -16,13 +16,21 @@ use embedded_hal::{
 };
 
 use crate::color::Color;
+use crate::error::{check_buffer_len, EpdError};
 use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::sequence::{Busy, Step};
+use crate::traits::{
+    Capabilities, InternalWiAdditions, RefreshLut, RefreshMode, WaveshareDisplay,
+    WaveshareStreamingDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
+#[cfg(feature = "async")]
+pub mod asynch;
+
 /// Full size buffer for use with the 7in5 v2 EPD
 #[cfg(feature = "graphics")]
 pub type Display7in5 = crate::graphics::Display<
@@ -41,6 +49,108 @@ pub const HEIGHT: u32 = 480;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
 
+const BUSY_SIGNAL: Busy<Command> = Busy {
+    is_busy_low: IS_BUSY_LOW,
+    status_command: Some(Command::GetStatus),
+};
+
+// V2 procedure as described here:
+// https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
+// and as per specs:
+// https://www.waveshare.com/w/upload/6/60/7.5inch_e-Paper_V2_Specification.pdf
+const INIT: &[Step<Command>] = &[
+    Step::Reset(10_000, 2_000),
+    Step::CmdWithData(Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17]),
+    Step::CmdWithData(Command::PowerSetting, &[0x07, 0x17, 0x3F, 0x3F]),
+    Step::Cmd(Command::PowerOn),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::PanelSetting, &[0x1F]),
+    Step::CmdWithData(Command::PllControl, &[0x06]),
+    Step::CmdWithData(Command::TconResolution, &RESOLUTION),
+    Step::CmdWithData(Command::DualSpi, &[0x00]),
+    Step::CmdWithData(Command::TconSetting, &[0x22]),
+    Step::CmdWithData(Command::VcomAndDataIntervalSetting, &[0x10, 0x07]),
+    Step::WaitUntilIdle,
+];
+
+const SLEEP: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::Cmd(Command::PowerOff),
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::DeepSleep, &[0xA5]),
+];
+
+const START_REFRESH: &[Step<Command>] = &[Step::WaitUntilIdle, Step::Cmd(Command::DisplayRefresh)];
+
+const CLEAR_FRAME: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::TconResolution, &RESOLUTION),
+    Step::Cmd(Command::DataStartTransmission1),
+    Step::DataXTimes(0x00, WIDTH * HEIGHT / 8),
+    Step::Cmd(Command::DataStartTransmission2),
+    Step::DataXTimes(0x00, WIDTH * HEIGHT / 8),
+    Step::Cmd(Command::DisplayRefresh),
+];
+
+// The fast and partial modes of the Waveshare driver force the waveform of a higher temperature,
+// which is shorter
+const SET_FULL: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::VcomAndDataIntervalSetting, &[0x10, 0x07]),
+    Step::CmdWithData(Command::CascadeSetting, &[0x00]),
+];
+
+const SET_FAST_FULL: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::VcomAndDataIntervalSetting, &[0x10, 0x07]),
+    Step::CmdWithData(Command::CascadeSetting, &[0x02]),
+    Step::CmdWithData(Command::ForceTemperature, &[0x5A]),
+];
+
+// Also floats the border and copies the new frame into the old one after every refresh, so the
+// next refresh only drives the pixels that changed since
+const SET_PARTIAL: &[Step<Command>] = &[
+    Step::WaitUntilIdle,
+    Step::CmdWithData(Command::VcomAndDataIntervalSetting, &[0x98, 0x07]),
+    Step::CmdWithData(Command::CascadeSetting, &[0x02]),
+    Step::CmdWithData(Command::ForceTemperature, &[0x6E]),
+];
+
+const RESOLUTION: [u8; 4] = [
+    (WIDTH >> 8) as u8,
+    WIDTH as u8,
+    (HEIGHT >> 8) as u8,
+    HEIGHT as u8,
+];
+
+// Parameters of `PartialWindow`, or None if the window is out of bounds or `x` and `width` aren't
+// multiples of 8
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> Option<[u8; 9]> {
+    if !x.is_multiple_of(8)
+        || !width.is_multiple_of(8)
+        || width == 0
+        || height == 0
+        || x + width > WIDTH
+        || y + height > HEIGHT
+    {
+        return None;
+    }
+    let x_end = x + width - 1;
+    let y_end = y + height - 1;
+    Some([
+        (x >> 8) as u8,
+        x as u8,
+        (x_end >> 8) as u8,
+        x_end as u8,
+        (y >> 8) as u8,
+        y as u8,
+        (y_end >> 8) as u8,
+        y_end as u8,
+        // gates scan both inside and outside of the window
+        0x01,
+    ])
+}
+
 /// Epd7in5 (V2) driver
 ///
 pub struct Epd7in5<SPI, CS, BUSY, DC, RST, DELAY> {
@@ -55,32 +165,17 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST,
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        // Reset the device
-        self.interface.reset(delay, 10_000, 2_000);
-
-        // V2 procedure as described here:
-        // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
-        // and as per specs:
-        // https://www.waveshare.com/w/upload/6/60/7.5inch_e-Paper_V2_Specification.pdf
-
-        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17])?;
-        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x17, 0x3F, 0x3F])?;
-        self.command(spi, Command::PowerOn)?;
-        self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])?;
-        self.cmd_with_data(spi, Command::PllControl, &[0x06])?;
-        self.cmd_with_data(spi, Command::TconResolution, &[0x03, 0x20, 0x01, 0xE0])?;
-        self.cmd_with_data(spi, Command::DualSpi, &[0x00])?;
-        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x10, 0x07])?;
-        self.wait_until_idle(spi, delay)?;
-        Ok(())
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, INIT)
     }
 }
 
@@ -89,12 +184,26 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DEL
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     type DisplayColor = Color;
+
+    #[cfg(feature = "graphics")]
+    type Display = Display7in5;
+    const WIDTH: u32 = WIDTH;
+    const HEIGHT: u32 = HEIGHT;
+    const BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+    const IS_BUSY_LOW: bool = IS_BUSY_LOW;
+    const CAPABILITIES: Capabilities = Capabilities {
+        partial_update: true,
+        partial_refresh: true,
+        fast_full_refresh: true,
+        ..Capabilities::BASIC
+    };
+
     fn new(
         spi: &mut SPI,
         cs: CS,
@@ -103,7 +212,7 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>> {
         let interface = DisplayInterface::new(cs, busy, dc, rst, delay_us);
         let color = DEFAULT_BACKGROUND_COLOR;
 
@@ -114,16 +223,20 @@ where
         Ok(epd)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.command(spi, Command::PowerOff)?;
-        self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
-        Ok(())
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, SLEEP)
     }
 
     fn update_frame(
@@ -131,29 +244,58 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        check_buffer_len(buffer, buffer_len(WIDTH as usize, HEIGHT as usize))?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
         Ok(())
     }
 
+    /// `x` and `width` need to be multiples of 8, otherwise [`EpdError::InvalidWindow`] is
+    /// returned.
+    ///
+    /// Only the window is written, the refresh still covers the whole panel. Use
+    /// [`RefreshMode::Partial`] to only drive the pixels that changed.
     fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let window = partial_window(x, y, width, height).ok_or(EpdError::InvalidWindow)?;
+        check_buffer_len(buffer, buffer_len(width as usize, height as usize))?;
+        self.wait_until_idle(spi, delay)?;
+
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.command(spi, Command::PartialOut)
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.start_refresh(spi, delay)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, START_REFRESH)
+    }
+
+    /// Sends `GetStatus` first, the controller only updates BUSY after it
+    fn is_busy(&mut self, spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.interface
+            .is_busy_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
     }
 
     fn update_and_display_frame(
@@ -161,24 +303,18 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_until_idle(spi, delay)?;
-        self.send_resolution(spi)?;
-
-        self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0x00, WIDTH * HEIGHT / 8)?;
-
-        self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, 0x00, WIDTH * HEIGHT / 8)?;
-
-        self.command(spi, Command::DisplayRefresh)?;
-        Ok(())
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.run(spi, delay, BUSY_SIGNAL, CLEAR_FRAME)
     }
 
     fn set_background_color(&mut self, color: Color) {
@@ -189,6 +325,14 @@ where
         &self.color
     }
 
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.interface.set_busy_timeout(max_ticks);
+    }
+
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>) {
+        self.interface.set_max_transfer(max_bytes);
+    }
+
     fn width(&self) -> u32 {
         WIDTH
     }
@@ -197,18 +341,43 @@ where
         HEIGHT
     }
 
+    /// [`RefreshLut::Quick`] selects the faster waveform of [`RefreshMode::FastFull`]
     fn set_lut(
         &mut self,
-        _spi: &mut SPI,
-        _delay: &mut DELAY,
-        _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match refresh_rate {
+            Some(RefreshLut::Full) => self.interface.run(spi, delay, BUSY_SIGNAL, SET_FULL),
+            Some(RefreshLut::Quick) => self.interface.run(spi, delay, BUSY_SIGNAL, SET_FAST_FULL),
+            None => Ok(()),
+        }
     }
 
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.interface
-            .wait_until_idle_with_cmd(spi, delay, IS_BUSY_LOW, Command::GetStatus)
+    /// The first [`Partial`](RefreshMode::Partial) refresh after a full one may also redraw
+    /// pixels that didn't change, as the controller doesn't know the shown frame yet.
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let sequence = match mode {
+            RefreshMode::Full => SET_FULL,
+            RefreshMode::FastFull => SET_FAST_FULL,
+            RefreshMode::Partial => SET_PARTIAL,
+            RefreshMode::Grayscale => return Err(EpdError::Unsupported),
+        };
+        self.interface.run(spi, delay, BUSY_SIGNAL, sequence)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.wait_until_idle_for(spi, delay, BUSY_SIGNAL)
     }
 }
 
@@ -216,37 +385,55 @@ impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
-    }
-
     fn cmd_with_data(
         &mut self,
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
+}
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
-        let w = self.width();
-        let h = self.height();
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission2)
+    }
 
-        self.command(spi, Command::TconResolution)?;
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.interface.data(spi, data)
     }
 }
 
@@ -260,4 +447,93 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_buffer_len() {
+        crate::graphics::assert_display_buffer_len::<Epd7in5<_, _, _, _, _, _>>();
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn refresh_modes() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+
+        assert!(epd.supports_refresh_mode(RefreshMode::FastFull));
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::FastFull)
+            .unwrap();
+        // CascadeSetting and ForceTemperature select the faster waveform
+        assert_eq!(sim.params(0xE0).as_deref(), Some(&[0x02][..]));
+        assert_eq!(sim.params(0xE5).as_deref(), Some(&[0x5A][..]));
+
+        assert!(!epd.supports_refresh_mode(RefreshMode::Grayscale));
+        assert_eq!(
+            epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Grayscale),
+            Err(EpdError::Unsupported)
+        );
+        assert_eq!(sim.params(0xE5).as_deref(), Some(&[0x5A][..]));
+
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Full)
+            .unwrap();
+        assert_eq!(sim.params(0xE0).as_deref(), Some(&[0x00][..]));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn partial_window() {
+        use crate::simulator::{Controller, SimDelay, Simulator};
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd7in5::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+
+        // the border floats and only changed pixels are driven
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Partial)
+            .unwrap();
+        assert_eq!(sim.params(0x50).as_deref(), Some(&[0x98, 0x07][..]));
+
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &[0xFF; 2 * 3], 784, 8, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+        assert!(sim.shown_pixel(1, 784, 8));
+        assert!(sim.shown_pixel(1, 799, 10));
+        assert!(!sim.shown_pixel(1, 783, 8));
+        assert!(!sim.shown_pixel(1, 784, 7));
+        assert!(!sim.shown_pixel(1, 784, 11));
+
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut SimDelay, &[0xFF; 2 * 3], 788, 8, 16, 3),
+            Err(EpdError::InvalidWindow)
+        );
+        assert_eq!(
+            epd.update_partial_frame(&mut spi, &mut SimDelay, &[0xFF; 2 * 3], 792, 8, 16, 3),
+            Err(EpdError::InvalidWindow)
+        );
+
+        epd.set_refresh_mode(&mut spi, &mut SimDelay, RefreshMode::Full)
+            .unwrap();
+        assert_eq!(sim.params(0x50).as_deref(), Some(&[0x10, 0x07][..]));
+    }
 }
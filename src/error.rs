@@ -0,0 +1,105 @@
+//! Error type shared by all drivers
+
+use core::fmt;
+
+/// Errors that can occur while talking to an e-paper display
+///
+/// `SpiE` is the error type of the SPI bus and `PinE` the (shared) error
+/// type of the CS, BUSY, DC and RST pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpdError<SpiE, PinE> {
+    /// The SPI bus returned an error
+    Spi(SpiE),
+    /// Setting or reading one of the control pins failed
+    Pin(PinE),
+    /// The BUSY line didn't signal idle within the configured time
+    BusyTimeout,
+    /// The supplied buffer doesn't match the size required by the operation
+    BufferSize {
+        /// Number of bytes the operation needs
+        expected: usize,
+        /// Number of bytes that were supplied
+        actual: usize,
+    },
+    /// The requested window lies outside the display or isn't aligned the way
+    /// the controller requires
+    InvalidWindow,
+    /// The operation isn't supported by this display or in its current mode
+    Unsupported,
+    /// The supplied look-up table has the wrong length for this controller
+    LutSize {
+        /// Number of bytes the controller expects
+        expected: usize,
+        /// Number of bytes that were supplied
+        actual: usize,
+    },
+}
+
+impl<SpiE: fmt::Debug, PinE: fmt::Debug> fmt::Display for EpdError<SpiE, PinE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::Spi(e) => write!(f, "SPI error: {:?}", e),
+            EpdError::Pin(e) => write!(f, "pin error: {:?}", e),
+            EpdError::BusyTimeout => write!(f, "timed out waiting for the display to become idle"),
+            EpdError::BufferSize { expected, actual } => write!(
+                f,
+                "buffer has {} bytes but {} bytes are required",
+                actual, expected
+            ),
+            EpdError::InvalidWindow => write!(f, "window is out of bounds or misaligned"),
+            EpdError::Unsupported => write!(f, "operation not supported in the current mode"),
+            EpdError::LutSize { expected, actual } => write!(
+                f,
+                "look-up table has {} bytes but {} bytes are required",
+                actual, expected
+            ),
+        }
+    }
+}
+
+/// Returns a [`EpdError::BufferSize`] error if `buffer` isn't exactly `expected` bytes long
+pub(crate) fn check_buffer_len<SpiE, PinE>(
+    buffer: &[u8],
+    expected: usize,
+) -> Result<(), EpdError<SpiE, PinE>> {
+    if buffer.len() == expected {
+        Ok(())
+    } else {
+        Err(EpdError::BufferSize {
+            expected,
+            actual: buffer.len(),
+        })
+    }
+}
+
+/// Returns a [`EpdError::LutSize`] error if `lut` isn't exactly `expected` bytes long
+pub(crate) fn check_lut_len<SpiE, PinE>(
+    lut: &[u8],
+    expected: usize,
+) -> Result<(), EpdError<SpiE, PinE>> {
+    if lut.len() == expected {
+        Ok(())
+    } else {
+        Err(EpdError::LutSize {
+            expected,
+            actual: lut.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_len_check() {
+        assert_eq!(check_buffer_len::<(), ()>(&[0; 4], 4), Ok(()));
+        assert_eq!(
+            check_buffer_len::<(), ()>(&[0; 3], 4),
+            Err(EpdError::BufferSize {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+}
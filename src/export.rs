@@ -0,0 +1,250 @@
+//! Export display buffers as images
+//!
+//! [`Display`] and [`VarDisplay`] buffers can be written as PBM, PPM or PNG files, e.g. to keep
+//! golden images of screens in CI. Pixels are converted with the existing `Rgb888` conversions
+//! of [`Color`](crate::color::Color), [`TriColor`](crate::color::TriColor) and
+//! [`OctColor`](crate::color::OctColor), so the chromatic color of a tricolor display is shown
+//! as red.
+//!
+//! Images always show the unrotated buffer, as it is sent to the display.
+//!
+//!```rust
+//!# fn main() -> std::io::Result<()> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd2in13bc::Display2in13bc, prelude::*};
+//!
+//!let mut display = Display2in13bc::default();
+//!Line::new(Point::new(0, 0), Point::new(20, 20))
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut display)
+//!    .unwrap();
+//!
+//!let mut png = Vec::new();
+//!display.write_png(&mut png)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::color::ColorType;
+use crate::graphics::{get_pixel, Display, VarDisplay};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use std::io::{Result, Write};
+use std::vec::Vec;
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType + Into<Rgb888>,
+    > Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    /// Write the buffer as a binary PBM image, every pixel darker than mid gray is black
+    pub fn write_pbm<W: Write>(&self, writer: W) -> Result<()> {
+        write_pbm::<COLOR, W>(self.buffer(), WIDTH, HEIGHT, writer)
+    }
+
+    /// Write the buffer as a binary PPM image
+    pub fn write_ppm<W: Write>(&self, writer: W) -> Result<()> {
+        write_ppm::<COLOR, W>(self.buffer(), WIDTH, HEIGHT, writer)
+    }
+
+    /// Write the buffer as a PNG image
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<()> {
+        write_png::<COLOR, W>(self.buffer(), WIDTH, HEIGHT, writer)
+    }
+}
+
+impl<'a, COLOR: ColorType + Into<Rgb888>> VarDisplay<'a, COLOR> {
+    /// Write the buffer as a binary PBM image, every pixel darker than mid gray is black
+    pub fn write_pbm<W: Write>(&self, writer: W) -> Result<()> {
+        write_pbm::<COLOR, W>(self.buffer(), self.width, self.height, writer)
+    }
+
+    /// Write the buffer as a binary PPM image
+    pub fn write_ppm<W: Write>(&self, writer: W) -> Result<()> {
+        write_ppm::<COLOR, W>(self.buffer(), self.width, self.height, writer)
+    }
+
+    /// Write the buffer as a PNG image
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<()> {
+        write_png::<COLOR, W>(self.buffer(), self.width, self.height, writer)
+    }
+}
+
+// row by row colors of the unrotated buffer
+fn rows<COLOR: ColorType + Into<Rgb888>>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+) -> impl Iterator<Item = impl Iterator<Item = Rgb888> + '_> + '_ {
+    (0..height)
+        .map(move |y| (0..width).map(move |x| get_pixel::<COLOR>(buffer, width, x, y).into()))
+}
+
+fn write_pbm<COLOR: ColorType + Into<Rgb888>, W: Write>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    mut writer: W,
+) -> Result<()> {
+    write!(writer, "P4\n{} {}\n", width, height)?;
+    let mut line = Vec::with_capacity(width.div_ceil(8) as usize);
+    for row in rows::<COLOR>(buffer, width, height) {
+        line.clear();
+        for (x, color) in row.enumerate() {
+            if x % 8 == 0 {
+                line.push(0);
+            }
+            let luma = color.r() as u32 * 299 + color.g() as u32 * 587 + color.b() as u32 * 114;
+            // a set bit is black in PBM
+            if luma < 128 * 1000 {
+                *line.last_mut().unwrap() |= 0x80 >> (x % 8);
+            }
+        }
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
+
+fn write_ppm<COLOR: ColorType + Into<Rgb888>, W: Write>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    mut writer: W,
+) -> Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    for row in rows::<COLOR>(buffer, width, height) {
+        for color in row {
+            writer.write_all(&[color.r(), color.g(), color.b()])?;
+        }
+    }
+    Ok(())
+}
+
+fn write_png<COLOR: ColorType + Into<Rgb888>, W: Write>(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    mut writer: W,
+) -> Result<()> {
+    // 8 bit RGB, each row starts with filter type 0 (none)
+    let mut raw = Vec::with_capacity((height * (1 + 3 * width)) as usize);
+    for row in rows::<COLOR>(buffer, width, height) {
+        raw.push(0);
+        for color in row {
+            raw.extend_from_slice(&[color.r(), color.g(), color.b()]);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_chunk(&mut writer, b"IHDR", &header)?;
+    write_chunk(&mut writer, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(&mut writer, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(crc32(!0, kind), data);
+    writer.write_all(&(!crc).to_be_bytes())
+}
+
+// zlib stream made of uncompressed deflate blocks, images of displays are small enough
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 0xFFFF * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::*;
+    use embedded_graphics_core::prelude::*;
+
+    #[test]
+    fn pbm_of_bw_display() {
+        let mut display = Display::<10, 2, false, 4, Color>::default();
+        display.clear(Color::White).unwrap();
+        display.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        display.set_pixel(Pixel(Point::new(9, 1), Color::Black));
+
+        let mut pbm = Vec::new();
+        display.write_pbm(&mut pbm).unwrap();
+        assert_eq!(pbm, b"P4\n10 2\n\x80\x00\x00\x40");
+    }
+
+    #[test]
+    fn ppm_of_tricolor_display() {
+        for bwrbit in [false, true] {
+            let mut buffer = [0u8; 2];
+            let mut display = VarDisplay::<TriColor>::new(8, 1, &mut buffer, bwrbit).unwrap();
+            display.set_pixel(Pixel(Point::new(0, 0), TriColor::White));
+            display.set_pixel(Pixel(Point::new(2, 0), TriColor::Chromatic));
+
+            let mut ppm = Vec::new();
+            display.write_ppm(&mut ppm).unwrap();
+            assert_eq!(&ppm[..11], b"P6\n8 1\n255\n");
+            assert_eq!(&ppm[11..20], b"\xff\xff\xff\x00\x00\x00\xff\x00\x00");
+        }
+    }
+
+    #[test]
+    fn png_of_octcolor_display() {
+        let mut display = Display::<2, 1, false, 1, OctColor>::default();
+        display.set_pixel(Pixel(Point::new(0, 0), OctColor::Green));
+        display.set_pixel(Pixel(Point::new(1, 0), OctColor::Orange));
+
+        let mut png = Vec::new();
+        display.write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        // filter byte and both pixels, stored uncompressed
+        let pixels = [0, 0x00, 0xff, 0x00, 0xff, 0x80, 0x00];
+        assert!(png.windows(pixels.len()).any(|w| w == pixels));
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+    }
+}
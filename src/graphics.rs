@@ -1,13 +1,20 @@
 //! Graphics Support for EPDs
 
 use crate::color::{ColorType, TriColor};
+use crate::error::EpdError;
+use crate::traits::{WaveshareDisplay, WaveshareThreeColorDisplay};
 use core::marker::PhantomData;
-use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::{prelude::*, primitives::Rectangle};
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
 
 /// Display rotation, only 90° increments supported
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub enum DisplayRotation {
     /// No rotation
+    #[default]
     Rotate0,
     /// Rotate by 90 degrees clockwise
     Rotate90,
@@ -17,16 +24,10 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
-impl Default for DisplayRotation {
-    fn default() -> Self {
-        DisplayRotation::Rotate0
-    }
-}
-
 /// count the number of bytes per line knowing that it may contains padding bits
-const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
+pub(crate) const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
     // round to upper 8 bit count
-    (width as usize * bits_per_pixel + 7) / 8
+    (width as usize * bits_per_pixel).div_ceil(8)
 }
 
 /// Display bffer used for drawing with embedded graphics
@@ -35,10 +36,10 @@ const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
 /// - WIDTH: width in pixel when display is not rotated
 /// - HEIGHT: height in pixel when display is not rotated
 /// - BWRBIT: mandatory value of the B/W when chromatic bit is set, can be any value for non
-///           tricolor epd
+///   tricolor epd
 /// - COLOR: color type used by the target display
 /// - BYTECOUNT: This is redundant with prvious data and should be removed when const generic
-///              expressions are stabilized
+///   expressions are stabilized
 ///
 /// More on BWRBIT:
 ///
@@ -60,6 +61,7 @@ pub struct Display<
 > {
     buffer: [u8; BYTECOUNT],
     rotation: DisplayRotation,
+    dirty: DirtyTracker,
     _color: PhantomData<COLOR>,
 }
 
@@ -85,11 +87,49 @@ impl<
             // default color must be 0 for every bit in a pixel to make this work everywere
             buffer: [0u8; BYTECOUNT],
             rotation: DisplayRotation::default(),
+            dirty: DirtyTracker::default(),
             _color: PhantomData,
         }
     }
 }
 
+/// Full size display buffer of a driver, see [`WaveshareDisplay::Display`]
+///
+/// Lets generic code allocate the buffer matching a driver, draw on it and send it.
+pub trait DisplayBuffer:
+    DrawTarget<Error = core::convert::Infallible> + OriginDimensions + Default
+{
+    /// get internal buffer to use it (to draw in epd)
+    fn buffer(&self) -> &[u8];
+
+    /// Set the display rotation.
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+
+    /// Get current rotation
+    fn rotation(&self) -> DisplayRotation;
+}
+
+impl<
+        const WIDTH: u32,
+        const HEIGHT: u32,
+        const BWRBIT: bool,
+        const BYTECOUNT: usize,
+        COLOR: ColorType,
+    > DisplayBuffer for Display<WIDTH, HEIGHT, BWRBIT, BYTECOUNT, COLOR>
+{
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
 /// For use with embedded_grahics
 impl<
         const WIDTH: u32,
@@ -158,7 +198,7 @@ impl<
 
     /// Set a specific pixel color on this display
     pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
-        set_pixel(
+        let changed = set_pixel(
             &mut self.buffer,
             WIDTH,
             HEIGHT,
@@ -166,6 +206,69 @@ impl<
             BWRBIT,
             pixel,
         );
+        self.dirty.mark(changed);
+    }
+
+    /// Starts or stops recording which part of the buffer is changed by drawing
+    ///
+    /// Stopping also forgets the changes recorded so far.
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty = DirtyTracker {
+            enabled,
+            bounds: None,
+        };
+    }
+
+    /// Window containing all pixels drawn since tracking started or was last reset
+    ///
+    /// The window is in the coordinates of the unrotated display, as expected by
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame), and its left and right
+    /// edges are aligned to bytes of 8 pixels.
+    pub fn dirty_rect(&self) -> Option<Rectangle> {
+        self.dirty.rect(WIDTH)
+    }
+
+    /// Forgets the changes recorded so far
+    pub fn reset_dirty(&mut self) {
+        self.dirty.bounds = None;
+    }
+
+    /// Sends the [dirty_rect](Display::dirty_rect) with
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame) and resets the tracking
+    ///
+    /// The window is copied into `scratch` first, which needs room for the window's bytes; a
+    /// buffer as large as the display's always works. Returns the window that was sent, or `None`
+    /// if nothing changed. Call [display_frame](WaveshareDisplay::display_frame) afterwards to
+    /// show it.
+    ///
+    /// For colors with two buffers only the first one is sent, as `update_partial_frame` takes a
+    /// single plane: tricolor panels clear the chromatic part of the window. Use
+    /// [update_dirty_color_region](Display::update_dirty_color_region) to send both.
+    pub fn update_dirty_region<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<Option<Rectangle>, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        update_dirty_region::<COLOR, _, _>(
+            &self.buffer,
+            WIDTH,
+            &mut self.dirty,
+            scratch,
+            |window, planes| {
+                send_first_plane::<COLOR, _, _, _, _, _, _, _>(epd, spi, delay, window, planes)
+            },
+        )
     }
 }
 
@@ -182,17 +285,46 @@ impl<const WIDTH: u32, const HEIGHT: u32, const BWRBIT: bool, const BYTECOUNT: u
     pub fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.buffer.len() / 2..]
     }
+
+    /// Sends the [dirty_rect](Display::dirty_rect) of both buffers with
+    /// [update_partial_color_frame](WaveshareThreeColorDisplay::update_partial_color_frame) and
+    /// resets the tracking, see [`Display::update_dirty_region`]
+    pub fn update_dirty_color_region<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<Option<Rectangle>, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        update_dirty_region::<TriColor, _, _>(
+            &self.buffer,
+            WIDTH,
+            &mut self.dirty,
+            scratch,
+            |window, planes| send_color_planes(epd, spi, delay, window, planes),
+        )
+    }
 }
 
 /// Same as `Display`, except that its characteristics are defined at runtime.
 /// See display for documentation as everything is the same except that default
 /// is replaced by a `new` method.
 pub struct VarDisplay<'a, COLOR: ColorType> {
-    width: u32,
-    height: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
     bwrbit: bool,
     buffer: &'a mut [u8],
     rotation: DisplayRotation,
+    dirty: DirtyTracker,
     _color: PhantomData<COLOR>,
 }
 
@@ -250,6 +382,7 @@ impl<'a, COLOR: ColorType> VarDisplay<'a, COLOR> {
             bwrbit,
             buffer,
             rotation: DisplayRotation::default(),
+            dirty: DirtyTracker::default(),
             _color: PhantomData,
         };
         // enfore some constraints dynamicly
@@ -289,7 +422,7 @@ impl<'a, COLOR: ColorType> VarDisplay<'a, COLOR> {
     /// Set a specific pixel color on this display
     pub fn set_pixel(&mut self, pixel: Pixel<COLOR>) {
         let size = self.buffer_size();
-        set_pixel(
+        let changed = set_pixel(
             &mut self.buffer[..size],
             self.width,
             self.height,
@@ -297,6 +430,58 @@ impl<'a, COLOR: ColorType> VarDisplay<'a, COLOR> {
             self.bwrbit,
             pixel,
         );
+        self.dirty.mark(changed);
+    }
+
+    /// Starts or stops recording which part of the buffer is changed by drawing,
+    /// see [`Display::set_dirty_tracking`]
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty = DirtyTracker {
+            enabled,
+            bounds: None,
+        };
+    }
+
+    /// Window containing all pixels drawn since tracking started or was last reset,
+    /// see [`Display::dirty_rect`]
+    pub fn dirty_rect(&self) -> Option<Rectangle> {
+        self.dirty.rect(self.width)
+    }
+
+    /// Forgets the changes recorded so far
+    pub fn reset_dirty(&mut self) {
+        self.dirty.bounds = None;
+    }
+
+    /// Sends the [dirty_rect](VarDisplay::dirty_rect) with
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame) and resets the tracking,
+    /// see [`Display::update_dirty_region`]
+    pub fn update_dirty_region<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<Option<Rectangle>, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let size = self.buffer_size();
+        update_dirty_region::<COLOR, _, _>(
+            &self.buffer[..size],
+            self.width,
+            &mut self.dirty,
+            scratch,
+            |window, planes| {
+                send_first_plane::<COLOR, _, _, _, _, _, _, _>(epd, spi, delay, window, planes)
+            },
+        )
     }
 }
 
@@ -311,35 +496,74 @@ impl<'a> VarDisplay<'a, TriColor> {
     pub fn chromatic_buffer(&self) -> &[u8] {
         &self.buffer[self.buffer_size() / 2..self.buffer_size()]
     }
+
+    /// Sends the [dirty_rect](VarDisplay::dirty_rect) of both buffers with
+    /// [update_partial_color_frame](WaveshareThreeColorDisplay::update_partial_color_frame) and
+    /// resets the tracking, see [`Display::update_dirty_region`]
+    pub fn update_dirty_color_region<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<Option<Rectangle>, EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let size = self.buffer_size();
+        update_dirty_region::<TriColor, _, _>(
+            &self.buffer[..size],
+            self.width,
+            &mut self.dirty,
+            scratch,
+            |window, planes| send_color_planes(epd, spi, delay, window, planes),
+        )
+    }
+}
+
+// Maps a point drawn with the given rotation to the coordinates of the unrotated buffer
+pub(crate) fn rotate(
+    point: Point,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> (i32, i32) {
+    match rotation {
+        // as i32 = never use more than 2 billion pixel per line or per column
+        DisplayRotation::Rotate0 => (point.x, point.y),
+        DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
+        DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
+        DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
+    }
 }
 
 // This is a function to share code between `Display` and `VarDisplay`
 // It sets a specific pixel in a buffer to a given color.
 // The big number of parameters is due to the fact that it is an internal function to both
 // strctures.
-fn set_pixel<COLOR: ColorType>(
+pub(crate) fn set_pixel<COLOR: ColorType>(
     buffer: &mut [u8],
     width: u32,
     height: u32,
     rotation: DisplayRotation,
     bwrbit: bool,
     pixel: Pixel<COLOR>,
-) {
+) -> Option<(u32, u32)> {
     let Pixel(point, color) = pixel;
 
     // final coordinates
-    let (x, y) = match rotation {
-        // as i32 = never use more than 2 billion pixel per line or per column
-        DisplayRotation::Rotate0 => (point.x, point.y),
-        DisplayRotation::Rotate90 => (width as i32 - 1 - point.y, point.x),
-        DisplayRotation::Rotate180 => (width as i32 - 1 - point.x, height as i32 - 1 - point.y),
-        DisplayRotation::Rotate270 => (point.y, height as i32 - 1 - point.x),
-    };
+    let (x, y) = rotate(point, width, height, rotation);
 
     // Out of range check
-    if (x < 0) || (x >= width as i32) || (y < 0) || (y > height as i32) {
+    if (x < 0) || (x >= width as i32) || (y < 0) || (y >= height as i32) {
         // don't do anything in case of out of range
-        return;
+        return None;
     }
 
     let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
@@ -354,6 +578,217 @@ fn set_pixel<COLOR: ColorType>(
     } else {
         buffer[index] = buffer[index] & mask | bits as u8;
     }
+    Some((x as u32, y as u32))
+}
+
+// Bounding box of the pixels drawn since the last reset, in unrotated buffer coordinates
+#[derive(Clone, Copy, Default)]
+pub(crate) struct DirtyTracker {
+    enabled: bool,
+    // left, top, right, bottom, all inclusive
+    bounds: Option<(u32, u32, u32, u32)>,
+}
+
+impl DirtyTracker {
+    fn mark(&mut self, changed: Option<(u32, u32)>) {
+        let Some((x, y)) = changed else { return };
+        if !self.enabled {
+            return;
+        }
+        self.bounds = Some(match self.bounds {
+            None => (x, y, x, y),
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }
+        });
+    }
+
+    // the bounds with the left and right edges expanded to whole bytes of 8 pixels, without
+    // going past the right edge of a display `width` pixels wide
+    fn rect(&self, width: u32) -> Option<Rectangle> {
+        self.bounds.map(|(left, top, right, bottom)| {
+            let left = left / 8 * 8;
+            let right = ((right / 8 + 1) * 8).min(width);
+            Rectangle::new(
+                Point::new(left as i32, top as i32),
+                Size::new(right - left, bottom - top + 1),
+            )
+        })
+    }
+}
+
+// Copies the rows of `window` out of a buffer laid out like the ones of `Display` into `scratch`.
+// For colors with two buffers, the window of the second one follows the window of the first.
+// Returns the number of bytes written, or None if `scratch` is too small.
+pub(crate) fn copy_window<COLOR: ColorType>(
+    buffer: &[u8],
+    width: u32,
+    window: &Rectangle,
+    scratch: &mut [u8],
+) -> Option<usize> {
+    let bits = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+    let line = line_bytes(width, bits);
+    let start = window.top_left.x as usize * bits / 8;
+    let row_len = line_bytes(window.size.width, bits);
+    let rows = window.size.height as usize;
+    let len = row_len * rows * COLOR::BUFFER_COUNT;
+    if scratch.len() < len {
+        return None;
+    }
+
+    let plane_len = buffer.len() / COLOR::BUFFER_COUNT;
+    let planes = buffer.chunks_exact(plane_len);
+    for (plane, out) in planes.zip(scratch[..len].chunks_exact_mut(row_len * rows)) {
+        let first_row = window.top_left.y as usize;
+        for (row, out) in out.chunks_exact_mut(row_len).enumerate() {
+            let offset = (first_row + row) * line + start;
+            out.copy_from_slice(&plane[offset..offset + row_len]);
+        }
+    }
+    Some(len)
+}
+
+// Writes the rows of `window` copied with `copy_window` back into a buffer.
+pub(crate) fn paste_window<COLOR: ColorType>(
+    buffer: &mut [u8],
+    width: u32,
+    window: &Rectangle,
+    scratch: &[u8],
+) {
+    let bits = COLOR::BITS_PER_PIXEL_PER_BUFFER;
+    let line = line_bytes(width, bits);
+    let start = window.top_left.x as usize * bits / 8;
+    let row_len = line_bytes(window.size.width, bits);
+    let rows = window.size.height as usize;
+
+    let plane_len = buffer.len() / COLOR::BUFFER_COUNT;
+    let planes = buffer.chunks_exact_mut(plane_len);
+    for (plane, window_plane) in planes.zip(scratch.chunks_exact(row_len * rows)) {
+        let first_row = window.top_left.y as usize;
+        for (row, data) in window_plane.chunks_exact(row_len).enumerate() {
+            let offset = (first_row + row) * line + start;
+            plane[offset..offset + row_len].copy_from_slice(data);
+        }
+    }
+}
+
+// Shared by `Display` and `VarDisplay` like `set_pixel`: copies the dirty window of every plane
+// into `scratch` and hands the window and its bytes, plane after plane, to `send`
+fn update_dirty_region<COLOR, SpiE, PinE>(
+    buffer: &[u8],
+    width: u32,
+    dirty: &mut DirtyTracker,
+    scratch: &mut [u8],
+    send: impl FnOnce(&Rectangle, &[u8]) -> Result<(), EpdError<SpiE, PinE>>,
+) -> Result<Option<Rectangle>, EpdError<SpiE, PinE>>
+where
+    COLOR: ColorType,
+{
+    let Some(window) = dirty.rect(width) else {
+        return Ok(None);
+    };
+    let len = match copy_window::<COLOR>(buffer, width, &window, scratch) {
+        Some(len) => len,
+        None => {
+            return Err(EpdError::BufferSize {
+                expected: line_bytes(window.size.width, COLOR::BITS_PER_PIXEL_PER_BUFFER)
+                    * window.size.height as usize
+                    * COLOR::BUFFER_COUNT,
+                actual: scratch.len(),
+            })
+        }
+    };
+    send(&window, &scratch[..len])?;
+    dirty.bounds = None;
+    Ok(Some(window))
+}
+
+// Sends the first plane of a window copied by `update_dirty_region`, which is all
+// `update_partial_frame` takes
+fn send_first_plane<COLOR, EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    window: &Rectangle,
+    planes: &[u8],
+) -> Result<(), EpdError<SPI::Error, CS::Error>>
+where
+    COLOR: ColorType,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    epd.update_partial_frame(
+        spi,
+        delay,
+        &planes[..planes.len() / COLOR::BUFFER_COUNT],
+        window.top_left.x as u32,
+        window.top_left.y as u32,
+        window.size.width,
+        window.size.height,
+    )
+}
+
+// Sends both planes of a tricolor window copied by `update_dirty_region`
+fn send_color_planes<EPD, SPI, CS, BUSY, DC, RST, DELAY>(
+    epd: &mut EPD,
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    window: &Rectangle,
+    planes: &[u8],
+) -> Result<(), EpdError<SPI::Error, CS::Error>>
+where
+    EPD: WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    let (black, chromatic) = planes.split_at(planes.len() / 2);
+    epd.update_partial_color_frame(
+        spi,
+        delay,
+        black,
+        chromatic,
+        window.top_left.x as u32,
+        window.top_left.y as u32,
+        window.size.width,
+        window.size.height,
+    )
+}
+
+// Reads back the color of a pixel in unrotated buffer coordinates, shared like `set_pixel`
+#[cfg(feature = "std")]
+pub(crate) fn get_pixel<COLOR: ColorType>(buffer: &[u8], width: u32, x: u32, y: u32) -> COLOR {
+    let index = x as usize * COLOR::BITS_PER_PIXEL_PER_BUFFER / 8
+        + y as usize * line_bytes(width, COLOR::BITS_PER_PIXEL_PER_BUFFER);
+    let mut bits = buffer[index] as u16;
+    if COLOR::BUFFER_COUNT == 2 {
+        bits |= (buffer[index + buffer.len() / 2] as u16) << 8;
+    }
+    COLOR::from_bits(bits, x)
+}
+
+// Checks that the `Display` of a driver holds `BUFFER_LEN` bytes, used by the tests of the drivers
+#[cfg(test)]
+pub(crate) fn assert_display_buffer_len<EPD>()
+where
+    EPD: WaveshareDisplay<
+        embedded_hal_mock::spi::Mock,
+        embedded_hal_mock::pin::Mock,
+        embedded_hal_mock::pin::Mock,
+        embedded_hal_mock::pin::Mock,
+        embedded_hal_mock::pin::Mock,
+        embedded_hal_mock::delay::MockNoop,
+    >,
+{
+    assert_eq!(EPD::Display::default().buffer().len(), EPD::BUFFER_LEN);
 }
 
 #[cfg(test)]
@@ -382,6 +817,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dirty_rect_rotated_and_aligned() {
+        let mut display = Display::<200, 100, false, { 200 * 100 / 8 }, Color>::default();
+        display.set_pixel(Pixel(Point::new(0, 0), Color::Black));
+        assert_eq!(display.dirty_rect(), None);
+
+        display.set_dirty_tracking(true);
+        display.set_rotation(DisplayRotation::Rotate90);
+        // (10, 20) and (30, 25) rotated are (179, 10) and (174, 30) in the buffer
+        display.set_pixel(Pixel(Point::new(10, 20), Color::Black));
+        display.set_pixel(Pixel(Point::new(30, 25), Color::Black));
+        display.set_pixel(Pixel(Point::new(-1, 25), Color::Black));
+        assert_eq!(
+            display.dirty_rect(),
+            Some(Rectangle::new(Point::new(168, 10), Size::new(16, 21)))
+        );
+
+        display.reset_dirty();
+        assert_eq!(display.dirty_rect(), None);
+    }
+
+    #[test]
+    fn dirty_rect_clamped_to_width() {
+        // the last byte of a 122 pixel line only holds 2 pixels
+        let mut display = Display::<122, 4, false, { 16 * 4 }, Color>::default();
+        display.set_dirty_tracking(true);
+        display.set_pixel(Pixel(Point::new(121, 3), Color::Black));
+        assert_eq!(
+            display.dirty_rect(),
+            Some(Rectangle::new(Point::new(120, 3), Size::new(2, 1)))
+        );
+
+        let mut buffer = [0u8; 16 * 2];
+        let mut display = VarDisplay::<Color>::new(122, 2, &mut buffer, false).unwrap();
+        display.set_dirty_tracking(true);
+        display.set_pixel(Pixel(Point::new(100, 0), Color::Black));
+        display.set_pixel(Pixel(Point::new(120, 1), Color::Black));
+        assert_eq!(
+            display.dirty_rect(),
+            Some(Rectangle::new(Point::new(96, 0), Size::new(26, 2)))
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn dirty_region_planes() {
+        use crate::epd2in13bc::{Display2in13bc, Epd2in13bc, HEIGHT, WIDTH};
+        use crate::simulator::{Controller, SimDelay, Simulator};
+        use crate::traits::WaveshareDisplay;
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in13bc::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let mut display = Display2in13bc::default();
+        let mut scratch = [0u8; 16];
+        // the byte of pixel (10, 5) in each plane
+        let index = 5 * 13 + 1;
+
+        display.set_dirty_tracking(true);
+        display.set_pixel(Pixel(Point::new(10, 5), TriColor::Chromatic));
+        let window = display
+            .update_dirty_color_region(&mut epd, &mut spi, &mut SimDelay, &mut scratch)
+            .unwrap();
+        assert_eq!(
+            window,
+            Some(Rectangle::new(Point::new(8, 5), Size::new(8, 1)))
+        );
+        assert_eq!(sim.ram(0)[index], display.bw_buffer()[index]);
+        assert_eq!(sim.ram(1)[index], display.chromatic_buffer()[index]);
+        assert_ne!(sim.ram(1)[index], 0xFF);
+
+        // only the b/w plane goes through update_partial_frame, the chromatic one is cleared
+        display.set_pixel(Pixel(Point::new(10, 5), TriColor::Black));
+        display
+            .update_dirty_region(&mut epd, &mut spi, &mut SimDelay, &mut scratch)
+            .unwrap();
+        assert_eq!(sim.ram(0)[index], display.bw_buffer()[index]);
+        assert_eq!(sim.ram(1)[index], 0xFF);
+        assert_eq!(display.dirty_rect(), None);
+    }
+
+    #[test]
+    fn dirty_window_copy() {
+        let mut buffer = [0u8; 2 * 24 / 8 * 4];
+        let mut display = VarDisplay::<TriColor>::new(24, 4, &mut buffer, false).unwrap();
+        display.set_dirty_tracking(true);
+        display.set_pixel(Pixel(Point::new(9, 1), TriColor::White));
+        display.set_pixel(Pixel(Point::new(17, 2), TriColor::Chromatic));
+        let window = display.dirty_rect().unwrap();
+        assert_eq!(window, Rectangle::new(Point::new(8, 1), Size::new(16, 2)));
+
+        let mut scratch = [0xAA; 16];
+        let len = copy_window::<TriColor>(display.buffer(), 24, &window, &mut scratch);
+        assert_eq!(len, Some(8));
+        // the b/w rows of the window, then the chromatic ones
+        assert_eq!(
+            scratch[..8],
+            [0x40, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x40]
+        );
+        assert_eq!(
+            copy_window::<TriColor>(display.buffer(), 24, &window, &mut scratch[..7]),
+            None
+        );
+    }
+
     #[test]
     fn graphics_rotation_0() {
         let mut display = Display::<200, 200, false, { 200 * 200 / 8 }, Color>::default();
@@ -1,3 +1,5 @@
+use crate::error::EpdError;
+use crate::sequence::{Busy, Step};
 use crate::traits::Command;
 use core::marker::PhantomData;
 use embedded_hal::{
@@ -5,6 +7,17 @@ use embedded_hal::{
     digital::v2::*,
 };
 
+/// Size of the scratch buffer used to repeat a data byte
+const REPEAT_CHUNK: usize = 64;
+
+/// Be careful!! Linux has a default limit of 4096 bytes per spi transfer
+/// see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
+const DEFAULT_MAX_TRANSFER: Option<usize> = if cfg!(target_os = "linux") {
+    Some(4096)
+} else {
+    None
+};
+
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
 pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
@@ -22,15 +35,19 @@ pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
     rst: RST,
     /// number of ms the idle loop should sleep on
     delay_us: u32,
+    /// maximum number of idle loop iterations before a busy timeout, `None` waits forever
+    busy_timeout: Option<u32>,
+    /// maximum number of bytes per spi write, `None` sends everything at once
+    max_transfer: Option<usize>,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// Creates a new `DisplayInterface` struct
@@ -40,22 +57,41 @@ where
         // default delay of 10ms
         let delay_us = delay_us.unwrap_or(10_000);
         DisplayInterface {
-            _spi: PhantomData::default(),
-            _delay: PhantomData::default(),
+            _spi: PhantomData,
+            _delay: PhantomData,
             cs,
             busy,
             dc,
             rst,
             delay_us,
+            busy_timeout: None,
+            max_transfer: DEFAULT_MAX_TRANSFER,
         }
     }
 
+    /// Sets the maximum number of idle loop iterations (each sleeping `delay_us`) to wait
+    /// for the busy line before returning [`EpdError::BusyTimeout`]
+    pub(crate) fn set_busy_timeout(&mut self, max_ticks: Option<u32>) {
+        self.busy_timeout = max_ticks;
+    }
+
+    /// Sets the maximum number of bytes sent in a single spi write
+    ///
+    /// Larger transfers are split up while CS stays low.
+    pub(crate) fn set_max_transfer(&mut self, max_bytes: Option<usize>) {
+        self.max_transfer = max_bytes;
+    }
+
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
+    pub(crate) fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // low for commands
-        let _ = self.dc.set_low();
+        self.dc.set_low().map_err(EpdError::Pin)?;
 
         // Transfer the command over spi
         self.write(spi, &[command.address()])
@@ -64,16 +100,16 @@ where
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    pub(crate) fn data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // high for data
-        let _ = self.dc.set_high();
-
-        for val in data.iter().copied() {
-            // Transfer data one u8 at a time over spi
-            self.write(spi, &[val])?;
-        }
+        self.dc.set_high().map_err(EpdError::Pin)?;
 
-        Ok(())
+        // Transfer the whole slice while CS is low
+        self.write(spi, data)
     }
 
     /// Basic function for sending [Commands](Command) and the data belonging to it.
@@ -84,7 +120,7 @@ where
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.cmd(spi, command)?;
         self.data(spi, data)
     }
@@ -97,36 +133,81 @@ where
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         // high for data
-        let _ = self.dc.set_high();
-        // Transfer data (u8) over spi
-        for _ in 0..repetitions {
-            self.write(spi, &[val])?;
-        }
-        Ok(())
+        self.dc.set_high().map_err(EpdError::Pin)?;
+
+        // Transfer data (u8) over spi, repeating a small scratch buffer
+        let scratch = [val; REPEAT_CHUNK];
+        let chunk_len = self.chunk_len(REPEAT_CHUNK);
+        let mut remaining = repetitions as usize;
+
+        self.selected(|_| {
+            while remaining > 0 {
+                let len = remaining.min(chunk_len);
+                spi.write(&scratch[..len]).map_err(EpdError::Spi)?;
+                remaining -= len;
+            }
+            Ok(())
+        })
+    }
+
+    /// Sends data converted byte by byte with `map`, e.g. inverted or expanded to more bits
+    ///
+    /// The converted bytes are collected in a scratch buffer, so they're still sent in bulk.
+    pub(crate) fn data_mapped<const N: usize>(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+        map: impl Fn(u8) -> [u8; N],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // high for data
+        self.dc.set_high().map_err(EpdError::Pin)?;
+
+        let mut scratch = [0; REPEAT_CHUNK];
+        self.selected(|interface| {
+            for data_chunk in data.chunks(REPEAT_CHUNK / N) {
+                let mut len = 0;
+                for &val in data_chunk {
+                    scratch[len..len + N].copy_from_slice(&map(val));
+                    len += N;
+                }
+                // the scratch buffer can still be larger than `max_transfer`
+                for transfer in scratch[..len].chunks(interface.chunk_len(len)) {
+                    spi.write(transfer).map_err(EpdError::Spi)?;
+                }
+            }
+            Ok(())
+        })
     }
 
     // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        // activate spi with cs low
-        let _ = self.cs.set_low();
-
-        // transfer spi data
-        // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
-        // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
-        if cfg!(target_os = "linux") {
-            for data_chunk in data.chunks(4096) {
-                spi.write(data_chunk)?;
+    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.selected(|interface| {
+            // transfer spi data, split up into chunks of at most `max_transfer` bytes
+            for data_chunk in data.chunks(interface.chunk_len(data.len())) {
+                spi.write(data_chunk).map_err(EpdError::Spi)?;
             }
-        } else {
-            spi.write(data)?;
-        }
+            Ok(())
+        })
+    }
 
+    // runs `transfer` with cs low, and releases cs again even if it fails
+    fn selected(
+        &mut self,
+        transfer: impl FnOnce(&Self) -> Result<(), EpdError<SPI::Error, CS::Error>>,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        // activate spi with cs low
+        self.cs.set_low().map_err(EpdError::Pin)?;
+        let result = transfer(self);
         // deactivate spi with cs high
-        let _ = self.cs.set_high();
+        let released = self.cs.set_high().map_err(EpdError::Pin);
+        result.and(released)
+    }
 
-        Ok(())
+    // number of bytes to write at once, limited by `max_transfer`
+    fn chunk_len(&self, len: usize) -> usize {
+        self.max_transfer.map_or(len, |max| len.min(max)).max(1)
     }
 
     /// Waits until device isn't busy anymore (busy == HIGH)
@@ -141,8 +222,14 @@ where
     ///  - FALSE for epd2in9, epd1in54 (for all Display Type A ones?)
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
-    pub(crate) fn wait_until_idle(&mut self, delay: &mut DELAY, is_busy_low: bool) {
-        while self.is_busy(is_busy_low) {
+    pub(crate) fn wait_until_idle(
+        &mut self,
+        delay: &mut DELAY,
+        is_busy_low: bool,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        let mut ticks = 0;
+        while self.is_busy(is_busy_low)? {
+            self.tick(&mut ticks)?;
             // This has been removed and added many time :
             // - it is faster to not have it
             // - it is complicated to pass the delay everywhere all the time
@@ -153,6 +240,7 @@ where
                 delay.delay_us(self.delay_us);
             }
         }
+        Ok(())
     }
 
     /// Same as `wait_until_idle` for device needing a command to probe Busy pin
@@ -162,12 +250,14 @@ where
         delay: &mut DELAY,
         is_busy_low: bool,
         status_command: T,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
         self.cmd(spi, status_command)?;
         if self.delay_us > 0 {
             delay.delay_us(self.delay_us);
         }
-        while self.is_busy(is_busy_low) {
+        let mut ticks = 0;
+        while self.is_busy(is_busy_low)? {
+            self.tick(&mut ticks)?;
             self.cmd(spi, status_command)?;
             if self.delay_us > 0 {
                 delay.delay_us(self.delay_us);
@@ -176,6 +266,66 @@ where
         Ok(())
     }
 
+    /// Same as `is_busy` for device needing a command to probe Busy pin
+    pub(crate) fn is_busy_with_cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        is_busy_low: bool,
+        status_command: T,
+    ) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        self.cmd(spi, status_command)?;
+        self.is_busy(is_busy_low)
+    }
+
+    /// Counts one idle loop iteration and fails once the busy timeout is exceeded
+    fn tick(&self, ticks: &mut u32) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match self.busy_timeout {
+            Some(max_ticks) if *ticks >= max_ticks => Err(EpdError::BusyTimeout),
+            _ => {
+                *ticks += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Waits until the device isn't busy anymore, the way described by `busy`
+    pub(crate) fn wait_until_idle_for<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        busy: Busy<T>,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match busy.status_command {
+            Some(status_command) => {
+                self.wait_until_idle_with_cmd(spi, delay, busy.is_busy_low, status_command)
+            }
+            None => self.wait_until_idle(delay, busy.is_busy_low),
+        }
+    }
+
+    /// Runs a command sequence shared with the async drivers
+    pub(crate) fn run<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        busy: Busy<T>,
+        steps: &[Step<'_, T>],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        for step in steps {
+            match *step {
+                Step::Reset(initial_delay, duration) => {
+                    self.reset(delay, initial_delay, duration)?
+                }
+                Step::Cmd(command) => self.cmd(spi, command)?,
+                Step::CmdWithData(command, data) => self.cmd_with_data(spi, command, data)?,
+                Step::DataXTimes(val, repetitions) => self.data_x_times(spi, val, repetitions)?,
+                Step::Delay(us) => delay.delay_us(us),
+                Step::WaitUntilIdle => self.wait_until_idle_for(spi, delay, busy)?,
+            }
+        }
+        Ok(())
+    }
+
     /// Checks if device is still busy
     ///
     /// This is normally handled by the more complicated commands themselves,
@@ -189,9 +339,15 @@ where
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
-    pub(crate) fn is_busy(&self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+    pub(crate) fn is_busy(
+        &self,
+        is_busy_low: bool,
+    ) -> Result<bool, EpdError<SPI::Error, CS::Error>> {
+        if is_busy_low {
+            self.busy.is_low().map_err(EpdError::Pin)
+        } else {
+            self.busy.is_high().map_err(EpdError::Pin)
+        }
     }
 
     /// Resets the device.
@@ -201,15 +357,183 @@ where
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
-    pub(crate) fn reset(&mut self, delay: &mut DELAY, initial_delay: u32, duration: u32) {
-        let _ = self.rst.set_high();
+    pub(crate) fn reset(
+        &mut self,
+        delay: &mut DELAY,
+        initial_delay: u32,
+        duration: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.rst.set_high().map_err(EpdError::Pin)?;
         delay.delay_us(initial_delay);
 
-        let _ = self.rst.set_low();
+        self.rst.set_low().map_err(EpdError::Pin)?;
         delay.delay_us(duration);
-        let _ = self.rst.set_high();
+        self.rst.set_high().map_err(EpdError::Pin)?;
         //TODO: the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
         delay.delay_us(200_000);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal_mock::{
+        delay::MockNoop,
+        pin::{Mock as PinMock, State, Transaction},
+        spi::{Mock as SpiMock, Transaction as SpiTransaction},
+    };
+
+    extern crate std;
+    use std::vec;
+
+    #[test]
+    fn busy_timeout() {
+        let busy = PinMock::new(&[
+            Transaction::get(State::High),
+            Transaction::get(State::High),
+            Transaction::get(State::High),
+        ]);
+        let mut interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[]),
+            busy,
+            PinMock::new(&[]),
+            PinMock::new(&[]),
+            None,
+        );
+        interface.set_busy_timeout(Some(2));
+        assert_eq!(
+            interface.wait_until_idle(&mut MockNoop::new(), false),
+            Err(EpdError::BusyTimeout)
+        );
+    }
+
+    #[test]
+    fn is_busy_polarity() {
+        let busy = PinMock::new(&[Transaction::get(State::Low), Transaction::get(State::Low)]);
+        let interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[]),
+            busy,
+            PinMock::new(&[]),
+            PinMock::new(&[]),
+            None,
+        );
+        assert_eq!(interface.is_busy(true), Ok(true));
+        assert_eq!(interface.is_busy(false), Ok(false));
+    }
+
+    #[test]
+    fn is_busy_sends_status_command() {
+        use crate::epd7in5_v2::command::Command;
+
+        let mut spi = SpiMock::new(&[SpiTransaction::write(vec![0x71])]);
+        let mut interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[Transaction::set(State::Low), Transaction::set(State::High)]),
+            PinMock::new(&[Transaction::get(State::Low)]),
+            PinMock::new(&[Transaction::set(State::Low)]),
+            PinMock::new(&[]),
+            None,
+        );
+        assert_eq!(
+            interface.is_busy_with_cmd(&mut spi, true, Command::GetStatus),
+            Ok(true)
+        );
+        spi.done();
+    }
+
+    #[test]
+    fn data_is_one_transfer() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::write(vec![1, 2, 3]),
+            SpiTransaction::write(vec![4]),
+        ]);
+        let mut interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[Transaction::set(State::Low), Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            PinMock::new(&[Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            None,
+        );
+        interface.set_max_transfer(Some(3));
+        interface.data(&mut spi, &[1, 2, 3, 4]).unwrap();
+        spi.done();
+    }
+
+    #[test]
+    fn data_x_times_repeats_scratch_buffer() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::write(vec![0xFF; REPEAT_CHUNK]),
+            SpiTransaction::write(vec![0xFF; 6]),
+        ]);
+        let mut interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[Transaction::set(State::Low), Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            PinMock::new(&[Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            None,
+        );
+        interface.set_max_transfer(None);
+        interface
+            .data_x_times(&mut spi, 0xFF, REPEAT_CHUNK as u32 + 6)
+            .unwrap();
+        spi.done();
+    }
+
+    #[test]
+    fn data_mapped_respects_max_transfer() {
+        let mut spi = SpiMock::new(&[
+            SpiTransaction::write(vec![1, 1, 2]),
+            SpiTransaction::write(vec![2, 3, 3]),
+        ]);
+        let mut interface: DisplayInterface<SpiMock, _, _, _, _, MockNoop> = DisplayInterface::new(
+            PinMock::new(&[Transaction::set(State::Low), Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            PinMock::new(&[Transaction::set(State::High)]),
+            PinMock::new(&[]),
+            None,
+        );
+        interface.set_max_transfer(Some(3));
+        interface
+            .data_mapped(&mut spi, &[1, 2, 3], |b| [b, b])
+            .unwrap();
+        spi.done();
+    }
+
+    struct FailingSpi;
+
+    impl Write<u8> for FailingSpi {
+        type Error = ();
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    #[test]
+    fn cs_is_released_after_spi_errors() {
+        let mut cs = PinMock::new(&[
+            Transaction::set(State::Low),
+            Transaction::set(State::High),
+            Transaction::set(State::Low),
+            Transaction::set(State::High),
+        ]);
+        let mut interface: DisplayInterface<FailingSpi, _, _, _, _, MockNoop> =
+            DisplayInterface::new(
+                cs.clone(),
+                PinMock::new(&[]),
+                PinMock::new(&[Transaction::set(State::High), Transaction::set(State::High)]),
+                PinMock::new(&[]),
+                None,
+            );
+        assert_eq!(
+            interface.data_mapped(&mut FailingSpi, &[1], |b| [!b]),
+            Err(EpdError::Spi(()))
+        );
+        assert_eq!(
+            interface.data(&mut FailingSpi, &[1]),
+            Err(EpdError::Spi(()))
+        );
+        cs.done();
     }
 }
@@ -12,7 +12,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -66,13 +66,53 @@
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
+#[cfg(feature = "graphics")]
+pub mod band;
+
+#[cfg(feature = "graphics")]
+pub mod dither;
+
+#[cfg(feature = "graphics")]
+pub mod diff;
+
+#[cfg(feature = "graphics")]
+pub mod double_buffer;
+
+pub mod lut;
+
+pub mod refresh_policy;
+
+pub mod any_epd;
+
 mod traits;
 
+mod error;
+
 pub mod color;
 
 /// Interface for the physical connection between display and the controlling device
 mod interface;
 
+mod sequence;
+
+#[cfg(feature = "async")]
+mod async_interface;
+
+#[cfg(feature = "async")]
+mod async_traits;
+
+#[cfg(feature = "embedded-hal-1")]
+pub mod eh1;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(all(feature = "std", feature = "graphics"))]
+pub mod export;
+
+#[cfg(feature = "simulator")]
+pub mod simulator;
+
 pub mod epd1in54;
 pub mod epd1in54_v2;
 pub mod epd1in54b;
@@ -99,14 +139,24 @@ pub(crate) mod type_a;
 /// Includes everything important besides the chosen Display
 pub mod prelude {
     pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::error::EpdError;
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        Capabilities, QuickRefresh, RefreshLut, RefreshMode, WaveshareCustomLut, WaveshareDisplay,
+        WaveshareGrayscaleDisplay, WaveshareStreamingDisplay, WaveshareThreeColorDisplay,
     };
 
+    #[cfg(feature = "graphics")]
+    pub use crate::color::Gray2;
+
     pub use crate::SPI_MODE;
 
+    #[cfg(feature = "async")]
+    pub use crate::async_traits::{
+        AsyncQuickRefresh, AsyncWaveshareDisplay, AsyncWaveshareThreeColorDisplay,
+    };
+
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation};
+    pub use crate::graphics::{Display, DisplayBuffer, DisplayRotation};
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
@@ -119,7 +169,7 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    width.div_ceil(8) * height
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
@@ -0,0 +1,437 @@
+//! Waveform look-up tables that can be uploaded at runtime
+//!
+//! The drivers ship the waveforms of the Waveshare reference code and only switch between a full
+//! and a quick one with [`RefreshLut`](crate::traits::RefreshLut). The types in this module model
+//! the tables of the different controller families, so you can tune your own waveforms (e.g. for
+//! a different temperature range) and load them with
+//! [`WaveshareCustomLut::set_custom_lut`](crate::traits::WaveshareCustomLut::set_custom_lut):
+//!
+//! - [`Il0373Lut`] for the VCOM/WW/BW/WB/BB group tables of the Epd 4in2 and 2in13 (D)
+//! - [`TypeALut`] for the 30 byte tables of the Epd 1in54 and 2in9
+//! - [`Ssd1680Lut`] for the 159 byte tables of the Epd 1in54 V2 and 2in9 V2
+//!
+//! Loaded tables stay active until the next [`set_lut`](crate::traits::WaveshareDisplay::set_lut)
+//! or [`wake_up`](crate::traits::WaveshareDisplay::wake_up).
+//!
+//! # Example
+//!
+//!```rust
+//! use epd_waveshare::lut::{Il0373Lut, Il0373Phase};
+//!
+//! // Drive black pixels to white and white ones to black, then hold
+//! let mut lut = Il0373Lut::default();
+//! lut.bw[0] = Il0373Phase { levels: 0b1000_0000, frames: [0x20, 0, 0, 0], repeat: 1 };
+//! lut.wb[0] = Il0373Phase { levels: 0b0100_0000, frames: [0x20, 0, 0, 0], repeat: 1 };
+//! lut.ww = lut.bw;
+//! lut.bb = lut.wb;
+//! assert_eq!(Il0373Lut::table_bytes(&lut.bw)[..6], [0x80, 0x20, 0, 0, 0, 1]);
+//!```
+
+use core::fmt;
+
+/// Errors found while validating a look-up table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LutError {
+    /// A table has the wrong number of bytes
+    Length {
+        /// Number of bytes the table needs
+        expected: usize,
+        /// Number of bytes that were supplied
+        actual: usize,
+    },
+    /// The gate driving voltage (VGH) is outside of `0x00..=0x17`
+    GateVoltage(u8),
+    /// A source driving voltage (VSH1, VSH2 or VSL) isn't one the controller accepts
+    SourceVoltage(u8),
+    /// The VCOM voltage is outside of `0x08..=0x78`
+    Vcom(u8),
+}
+
+impl fmt::Display for LutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LutError::Length { expected, actual } => write!(
+                f,
+                "look-up table has {} bytes but {} bytes are required",
+                actual, expected
+            ),
+            LutError::GateVoltage(v) => write!(f, "invalid gate driving voltage {:#04x}", v),
+            LutError::SourceVoltage(v) => write!(f, "invalid source driving voltage {:#04x}", v),
+            LutError::Vcom(v) => write!(f, "invalid VCOM voltage {:#04x}", v),
+        }
+    }
+}
+
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), LutError> {
+    if bytes.len() == expected {
+        Ok(())
+    } else {
+        Err(LutError::Length {
+            expected,
+            actual: bytes.len(),
+        })
+    }
+}
+
+/// One phase group of an IL0373 style table
+///
+/// Each group drives up to four levels, one after the other, and is repeated as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Il0373Phase {
+    /// Level selection of the four sub-phases, two bits each with the first sub-phase in the
+    /// top bits (`00` = GND/VCOM_DC, `01` = VDH, `10` = VDL, `11` = floating/VDHR)
+    pub levels: u8,
+    /// Number of frames of each sub-phase
+    pub frames: [u8; 4],
+    /// How often the group is repeated
+    pub repeat: u8,
+}
+
+/// Number of phase groups in an IL0373 style table
+pub const IL0373_PHASES: usize = 7;
+
+/// Look-up tables of the IL0373/UC8176 style controllers used by the Epd 4in2 and 2in13 (D)
+///
+/// The controller picks one of the four pixel tables from the old (previous frame) and the new
+/// value of every pixel, while the VCOM table drives the common electrode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Il0373Lut {
+    /// VCOM table
+    pub vcom: [Il0373Phase; IL0373_PHASES],
+    /// Table for pixels staying white
+    pub ww: [Il0373Phase; IL0373_PHASES],
+    /// Table for pixels going from black to white
+    pub bw: [Il0373Phase; IL0373_PHASES],
+    /// Table for pixels going from white to black
+    pub wb: [Il0373Phase; IL0373_PHASES],
+    /// Table for pixels staying black
+    pub bb: [Il0373Phase; IL0373_PHASES],
+}
+
+impl Il0373Lut {
+    /// Size of the VCOM table sent to the controller, including two trailing padding bytes
+    pub const VCOM_LEN: usize = 44;
+    /// Size of each of the pixel tables
+    pub const TABLE_LEN: usize = 6 * IL0373_PHASES;
+
+    /// Parses the raw tables in the order the controller expects them
+    ///
+    /// The VCOM table may be passed with or without its two padding bytes.
+    pub fn from_bytes(
+        vcom: &[u8],
+        ww: &[u8],
+        bw: &[u8],
+        wb: &[u8],
+        bb: &[u8],
+    ) -> Result<Self, LutError> {
+        let vcom = if vcom.len() == Self::VCOM_LEN {
+            &vcom[..Self::TABLE_LEN]
+        } else {
+            vcom
+        };
+        Ok(Il0373Lut {
+            vcom: Self::parse_table(vcom)?,
+            ww: Self::parse_table(ww)?,
+            bw: Self::parse_table(bw)?,
+            wb: Self::parse_table(wb)?,
+            bb: Self::parse_table(bb)?,
+        })
+    }
+
+    fn parse_table(bytes: &[u8]) -> Result<[Il0373Phase; IL0373_PHASES], LutError> {
+        check_len(bytes, Self::TABLE_LEN)?;
+        let mut table = [Il0373Phase::default(); IL0373_PHASES];
+        for (phase, raw) in table.iter_mut().zip(bytes.chunks_exact(6)) {
+            *phase = Il0373Phase {
+                levels: raw[0],
+                frames: [raw[1], raw[2], raw[3], raw[4]],
+                repeat: raw[5],
+            };
+        }
+        Ok(table)
+    }
+
+    /// Raw bytes of one of the pixel tables
+    pub fn table_bytes(table: &[Il0373Phase; IL0373_PHASES]) -> [u8; Self::TABLE_LEN] {
+        let mut bytes = [0; Self::TABLE_LEN];
+        for (raw, phase) in bytes.chunks_exact_mut(6).zip(table) {
+            raw[0] = phase.levels;
+            raw[1..5].copy_from_slice(&phase.frames);
+            raw[5] = phase.repeat;
+        }
+        bytes
+    }
+
+    /// Raw bytes of the VCOM table, padded to the length the controller expects
+    pub fn vcom_bytes(&self) -> [u8; Self::VCOM_LEN] {
+        let mut bytes = [0; Self::VCOM_LEN];
+        bytes[..Self::TABLE_LEN].copy_from_slice(&Self::table_bytes(&self.vcom));
+        bytes
+    }
+}
+
+/// 30 byte look-up table of the SSD1608/IL3820 controllers of the Epd 1in54 and 2in9
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TypeALut {
+    /// Source voltage of each of the 20 phases, two bits per old/new pixel combination
+    pub voltages: [u8; 20],
+    /// Phase lengths in frames, a nibble per phase with the first phase in the low nibble
+    pub timings: [u8; 10],
+}
+
+impl TypeALut {
+    /// Size of the table sent to the controller
+    pub const LEN: usize = 30;
+
+    /// Parses a raw table
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LutError> {
+        check_len(bytes, Self::LEN)?;
+        let mut lut = TypeALut::default();
+        lut.voltages.copy_from_slice(&bytes[..20]);
+        lut.timings.copy_from_slice(&bytes[20..]);
+        Ok(lut)
+    }
+
+    /// Raw bytes in the order the controller expects them
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[..20].copy_from_slice(&self.voltages);
+        bytes[20..].copy_from_slice(&self.timings);
+        bytes
+    }
+}
+
+/// One of the twelve phase groups of an SSD1680 table
+///
+/// Each group runs the sub-phases A and B, then C and D, and is repeated as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ssd1680Group {
+    /// Number of frames of the sub-phases A, B, C and D
+    pub frames: [u8; 4],
+    /// How often the sub-phases A and B are repeated
+    pub repeat_ab: u8,
+    /// How often the sub-phases C and D are repeated
+    pub repeat_cd: u8,
+    /// How often the whole group is repeated
+    pub repeat: u8,
+}
+
+/// Driving voltages set along with an SSD1680 table
+///
+/// Only values the controller accepts can be constructed, see [`Ssd1680Voltages::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ssd1680Voltages {
+    gate: u8,
+    source: [u8; 3],
+    vcom: u8,
+}
+
+impl Ssd1680Voltages {
+    /// Checks and combines the register values of the driving voltages
+    ///
+    /// - `gate` (VGH) must be in `0x00..=0x17`
+    /// - `vsh1` and `vsh2` must be in `0x23..=0x50` or `0x8E..=0xCE`, or `0x00` to leave it unused
+    /// - `vsl` must be even and in `0x1A..=0x3A`
+    /// - `vcom` must be in `0x08..=0x78`
+    pub fn new(gate: u8, vsh1: u8, vsh2: u8, vsl: u8, vcom: u8) -> Result<Self, LutError> {
+        if gate > 0x17 {
+            return Err(LutError::GateVoltage(gate));
+        }
+        for vsh in [vsh1, vsh2] {
+            if !matches!(vsh, 0x00 | 0x23..=0x50 | 0x8E..=0xCE) {
+                return Err(LutError::SourceVoltage(vsh));
+            }
+        }
+        if !matches!(vsl, 0x1A..=0x3A) || !vsl.is_multiple_of(2) {
+            return Err(LutError::SourceVoltage(vsl));
+        }
+        if !matches!(vcom, 0x08..=0x78) {
+            return Err(LutError::Vcom(vcom));
+        }
+        Ok(Ssd1680Voltages {
+            gate,
+            source: [vsh1, vsh2, vsl],
+            vcom,
+        })
+    }
+
+    /// Gate driving voltage (VGH) register value
+    pub fn gate(&self) -> u8 {
+        self.gate
+    }
+
+    /// Source driving voltages (VSH1, VSH2, VSL) register values
+    pub fn source(&self) -> [u8; 3] {
+        self.source
+    }
+
+    /// VCOM register value
+    pub fn vcom(&self) -> u8 {
+        self.vcom
+    }
+}
+
+/// Number of phase groups in an SSD1680 table
+pub const SSD1680_GROUPS: usize = 12;
+
+/// 159 byte look-up table of the SSD1680/SSD1681 controllers of the Epd 1in54 V2 and 2in9 V2
+///
+/// This is the 153 byte waveform followed by the end option and the driving voltages, in the
+/// layout of the Waveshare reference tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ssd1680Lut {
+    /// Voltage selection of the five LUTs (one per old/new pixel combination and VCOM), with the
+    /// sub-phases A to D of a group in one byte, two bits each
+    pub levels: [[u8; SSD1680_GROUPS]; 5],
+    /// Timing of the phase groups
+    pub groups: [Ssd1680Group; SSD1680_GROUPS],
+    /// Frame rate settings, a nibble per group
+    pub frame_rates: [u8; 6],
+    /// Gate scan selection (XON), a bit per sub-phase
+    pub gate_xon: [u8; 3],
+    /// End option (EOPT) of the waveform
+    pub end_option: u8,
+    /// Driving voltages
+    pub voltages: Ssd1680Voltages,
+}
+
+impl Ssd1680Lut {
+    /// Size of the raw table including the end option and voltages
+    pub const LEN: usize = 159;
+    /// Size of the waveform part written with the LUT register command
+    pub const WAVEFORM_LEN: usize = 153;
+
+    /// Parses and checks a raw table
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LutError> {
+        check_len(bytes, Self::LEN)?;
+        let mut levels = [[0; SSD1680_GROUPS]; 5];
+        for (lut, raw) in levels
+            .iter_mut()
+            .zip(bytes[..60].chunks_exact(SSD1680_GROUPS))
+        {
+            lut.copy_from_slice(raw);
+        }
+        let mut groups = [Ssd1680Group::default(); SSD1680_GROUPS];
+        for (group, raw) in groups.iter_mut().zip(bytes[60..144].chunks_exact(7)) {
+            *group = Ssd1680Group {
+                frames: [raw[0], raw[1], raw[3], raw[4]],
+                repeat_ab: raw[2],
+                repeat_cd: raw[5],
+                repeat: raw[6],
+            };
+        }
+        let mut frame_rates = [0; 6];
+        frame_rates.copy_from_slice(&bytes[144..150]);
+        let mut gate_xon = [0; 3];
+        gate_xon.copy_from_slice(&bytes[150..153]);
+        Ok(Ssd1680Lut {
+            levels,
+            groups,
+            frame_rates,
+            gate_xon,
+            end_option: bytes[153],
+            voltages: Ssd1680Voltages::new(
+                bytes[154], bytes[155], bytes[156], bytes[157], bytes[158],
+            )?,
+        })
+    }
+
+    /// Raw waveform bytes written with the LUT register command
+    pub fn waveform_bytes(&self) -> [u8; Self::WAVEFORM_LEN] {
+        let mut bytes = [0; Self::WAVEFORM_LEN];
+        for (raw, lut) in bytes[..60]
+            .chunks_exact_mut(SSD1680_GROUPS)
+            .zip(&self.levels)
+        {
+            raw.copy_from_slice(lut);
+        }
+        for (raw, group) in bytes[60..144].chunks_exact_mut(7).zip(&self.groups) {
+            raw.copy_from_slice(&[
+                group.frames[0],
+                group.frames[1],
+                group.repeat_ab,
+                group.frames[2],
+                group.frames[3],
+                group.repeat_cd,
+                group.repeat,
+            ]);
+        }
+        bytes[144..150].copy_from_slice(&self.frame_rates);
+        bytes[150..].copy_from_slice(&self.gate_xon);
+        bytes
+    }
+
+    /// Raw bytes in the layout of the Waveshare reference tables
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[..Self::WAVEFORM_LEN].copy_from_slice(&self.waveform_bytes());
+        bytes[153] = self.end_option;
+        bytes[154] = self.voltages.gate;
+        bytes[155..158].copy_from_slice(&self.voltages.source);
+        bytes[158] = self.voltages.vcom;
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssd1680_round_trip() {
+        let raw = crate::epd1in54_v2::constants::LUT_FULL_UPDATE;
+        let lut = Ssd1680Lut::from_bytes(&raw).unwrap();
+        assert_eq!(lut.groups[1].frames, [0x08, 0x01, 0x08, 0x01]);
+        assert_eq!(lut.groups[1].repeat, 0x02);
+        assert_eq!(lut.voltages.source(), [0x41, 0x00, 0x32]);
+        assert_eq!(lut.to_bytes(), raw);
+    }
+
+    #[test]
+    fn ssd1680_rejects_bad_voltages() {
+        let mut raw = crate::epd1in54_v2::constants::LUT_PARTIAL_UPDATE;
+        raw[154] = 0x18;
+        assert_eq!(
+            Ssd1680Lut::from_bytes(&raw),
+            Err(LutError::GateVoltage(0x18))
+        );
+        assert_eq!(
+            Ssd1680Voltages::new(0x17, 0x41, 0xB0, 0x33, 0x28),
+            Err(LutError::SourceVoltage(0x33))
+        );
+        assert_eq!(
+            Ssd1680Voltages::new(0x17, 0x41, 0xB0, 0x32, 0x80),
+            Err(LutError::Vcom(0x80))
+        );
+        assert_eq!(
+            Ssd1680Lut::from_bytes(&raw[..158]),
+            Err(LutError::Length {
+                expected: 159,
+                actual: 158
+            })
+        );
+    }
+
+    #[test]
+    fn il0373_round_trip() {
+        use crate::epd4in2::constants::*;
+        let lut = Il0373Lut::from_bytes(&LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB).unwrap();
+        assert_eq!(lut.vcom_bytes(), LUT_VCOM0);
+        assert_eq!(Il0373Lut::table_bytes(&lut.bw), LUT_BW);
+        assert_eq!(
+            Il0373Lut::from_bytes(&LUT_VCOM0, &LUT_WW[..40], &LUT_BW, &LUT_WB, &LUT_BB),
+            Err(LutError::Length {
+                expected: 42,
+                actual: 40
+            })
+        );
+    }
+
+    #[test]
+    fn type_a_round_trip() {
+        let raw = crate::type_a::constants::LUT_PARTIAL_UPDATE;
+        let lut = TypeALut::from_bytes(&raw).unwrap();
+        assert_eq!(lut.timings[0], 0x13);
+        assert_eq!(lut.to_bytes(), raw);
+    }
+}
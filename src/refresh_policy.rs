@@ -0,0 +1,273 @@
+//! Forcing full refreshes after a number of quick ones
+//!
+//! Quick and partial refreshes leave some ghosting behind, which only a full refresh removes.
+//! A [`RefreshPolicy`] wraps a driver, counts the quick refreshes since the last full one and
+//! turns the next refresh into a full one once the configured budget is used up.
+//!
+//! The counters are kept in [`RefreshCounters`], which can be saved as a few bytes (e.g. in the
+//! RTC memory of the MCU) before going to deep sleep and restored with
+//! [`RefreshPolicy::with_counters`] after waking up.
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
+//!use epd_waveshare::{epd2in9::*, prelude::*, refresh_policy::{RefreshCounters, RefreshPolicy}};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!# let saved = [0u8; RefreshCounters::LEN];
+//!# let frame = [0xFFu8; WIDTH as usize / 8 * HEIGHT as usize];
+//!
+//!let epd = Epd2in9::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay, None)?;
+//!// Partial refreshes, with a full one after every 10 of them
+//!let mut epd = RefreshPolicy::with_counters(epd, 10, RefreshCounters::from_bytes(&saved));
+//!
+//!epd.update_and_display_frame(&mut spi, &frame, &mut delay)?;
+//!
+//!// Keep these somewhere that survives deep sleep
+//!let saved = epd.counters().to_bytes();
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::error::EpdError;
+use crate::traits::{QuickRefresh, RefreshMode, WaveshareDisplay};
+
+/// Refresh counters of a [`RefreshPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RefreshCounters {
+    /// Quick refreshes since the last full refresh
+    pub quick_refreshes: u32,
+    /// All refreshes so far, quick and full
+    pub refreshes: u32,
+    /// Whether the next refresh has to be a full one, regardless of the budget
+    pub full_refresh_pending: bool,
+}
+
+impl RefreshCounters {
+    /// Size of the serialized counters
+    pub const LEN: usize = 9;
+
+    /// Serializes the counters, e.g. to keep them over deep sleep
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0; Self::LEN];
+        bytes[0..4].copy_from_slice(&self.quick_refreshes.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.refreshes.to_le_bytes());
+        bytes[8] = self.full_refresh_pending as u8;
+        bytes
+    }
+
+    /// Restores counters saved with [`to_bytes`](RefreshCounters::to_bytes)
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Self {
+        RefreshCounters {
+            quick_refreshes: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            refreshes: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            full_refresh_pending: bytes[8] != 0,
+        }
+    }
+}
+
+/// Wrapper around a driver that inserts a full refresh after a budget of quick refreshes
+///
+/// [`update_and_display_frame`](RefreshPolicy::update_and_display_frame) works with the
+/// [`RefreshMode`]s of any [`WaveshareDisplay`], while
+/// [`update_and_display_new_frame`](RefreshPolicy::update_and_display_new_frame) uses the old/new
+/// frames of drivers implementing [`QuickRefresh`]. The driver itself stays reachable with
+/// [`epd`](RefreshPolicy::epd) and [`epd_mut`](RefreshPolicy::epd_mut).
+pub struct RefreshPolicy<EPD> {
+    epd: EPD,
+    max_quick_refreshes: u32,
+    quick_mode: RefreshMode,
+    counters: RefreshCounters,
+}
+
+impl<EPD> RefreshPolicy<EPD> {
+    /// Wraps `epd`, allowing `max_quick_refreshes` quick refreshes between two full ones
+    ///
+    /// The first refresh is a full one, as the state of the panel is unknown.
+    pub fn new(epd: EPD, max_quick_refreshes: u32) -> Self {
+        let counters = RefreshCounters {
+            full_refresh_pending: true,
+            ..RefreshCounters::default()
+        };
+        Self::with_counters(epd, max_quick_refreshes, counters)
+    }
+
+    /// Wraps `epd` and continues with the counters of an earlier policy
+    pub fn with_counters(epd: EPD, max_quick_refreshes: u32, counters: RefreshCounters) -> Self {
+        RefreshPolicy {
+            epd,
+            max_quick_refreshes,
+            quick_mode: RefreshMode::Partial,
+            counters,
+        }
+    }
+
+    /// Selects the mode used for quick refreshes, [`RefreshMode::Partial`] by default
+    ///
+    /// Displays that don't support the mode always refresh fully.
+    pub fn set_quick_mode(&mut self, mode: RefreshMode) {
+        self.quick_mode = mode;
+    }
+
+    /// Current counters
+    pub fn counters(&self) -> RefreshCounters {
+        self.counters
+    }
+
+    /// Whether the next refresh will be a full one
+    pub fn needs_full_refresh(&self) -> bool {
+        self.counters.full_refresh_pending
+            || self.counters.quick_refreshes >= self.max_quick_refreshes
+    }
+
+    /// Makes the next refresh a full one, e.g. after the display was woken up
+    pub fn request_full_refresh(&mut self) {
+        self.counters.full_refresh_pending = true;
+    }
+
+    /// Counts a refresh done directly on the driver
+    pub fn record_refresh(&mut self, full: bool) {
+        self.counters.refreshes = self.counters.refreshes.wrapping_add(1);
+        if full {
+            self.counters.quick_refreshes = 0;
+            self.counters.full_refresh_pending = false;
+        } else {
+            self.counters.quick_refreshes = self.counters.quick_refreshes.saturating_add(1);
+        }
+    }
+
+    /// The wrapped driver
+    pub fn epd(&self) -> &EPD {
+        &self.epd
+    }
+
+    /// The wrapped driver, for everything the policy doesn't cover
+    ///
+    /// Refreshes done this way aren't counted, see [`record_refresh`](RefreshPolicy::record_refresh).
+    pub fn epd_mut(&mut self) -> &mut EPD {
+        &mut self.epd
+    }
+
+    /// Returns the wrapped driver
+    pub fn into_inner(self) -> EPD {
+        self.epd
+    }
+
+    /// Transmits and displays a full frame, refreshing quickly while the budget allows it
+    ///
+    /// Quick refreshes use the mode set with [`set_quick_mode`](RefreshPolicy::set_quick_mode).
+    pub fn update_and_display_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let full = self.needs_full_refresh() || !self.epd.supports_refresh_mode(self.quick_mode);
+        let mode = if full {
+            RefreshMode::Full
+        } else {
+            self.quick_mode
+        };
+        self.epd.set_refresh_mode(spi, delay, mode)?;
+        self.epd.update_and_display_frame(spi, buffer, delay)?;
+        self.record_refresh(full);
+        Ok(())
+    }
+
+    /// Transmits and displays a new frame with [`QuickRefresh`] while the budget allows it
+    ///
+    /// Once the budget is used up, the frame is shown with a full refresh instead and also sent
+    /// as the old frame, so the following quick refreshes start from it.
+    pub fn update_and_display_new_frame<SPI, CS, BUSY, DC, RST, DELAY>(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    where
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+            + QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>,
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin<Error = CS::Error>,
+        DC: OutputPin<Error = CS::Error>,
+        RST: OutputPin<Error = CS::Error>,
+        DELAY: DelayUs<u32>,
+    {
+        let full = self.needs_full_refresh();
+        if full {
+            self.epd.set_refresh_mode(spi, delay, RefreshMode::Full)?;
+            self.epd.update_and_display_frame(spi, buffer, delay)?;
+            self.epd.update_old_frame(spi, buffer, delay)?;
+        } else {
+            self.epd.update_and_display_new_frame(spi, buffer, delay)?;
+        }
+        self.record_refresh(full);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_refresh_after_budget() {
+        let mut policy = RefreshPolicy::new((), 2);
+        assert!(policy.needs_full_refresh());
+        policy.record_refresh(true);
+        assert!(!policy.needs_full_refresh());
+        policy.record_refresh(false);
+        policy.record_refresh(false);
+        assert!(policy.needs_full_refresh());
+        policy.record_refresh(true);
+        assert_eq!(
+            policy.counters(),
+            RefreshCounters {
+                quick_refreshes: 0,
+                refreshes: 4,
+                full_refresh_pending: false,
+            }
+        );
+        policy.request_full_refresh();
+        assert!(policy.needs_full_refresh());
+    }
+
+    #[test]
+    fn counters_round_trip() {
+        let counters = RefreshCounters {
+            quick_refreshes: 7,
+            refreshes: 0x0102_0304,
+            full_refresh_pending: true,
+        };
+        let bytes = counters.to_bytes();
+        assert_eq!(bytes, [7, 0, 0, 0, 4, 3, 2, 1, 1]);
+        assert_eq!(RefreshCounters::from_bytes(&bytes), counters);
+
+        let policy = RefreshPolicy::with_counters((), 8, counters);
+        assert!(policy.needs_full_refresh());
+    }
+}
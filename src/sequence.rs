@@ -0,0 +1,34 @@
+//! Controller command sequences
+//!
+//! Fixed sequences (init, sleep, refresh, ...) are described as slices of [`Step`]s so that the
+//! blocking and the async drivers run exactly the same commands. Sequences depending on runtime
+//! values, like the ram window of a partial update, are built by functions returning arrays of
+//! steps which borrow their data.
+
+use crate::traits::Command;
+
+/// One step of a command sequence
+#[derive(Clone, Copy)]
+pub(crate) enum Step<'a, C: Command> {
+    /// Hardware reset with the given initial delay and reset duration in µs
+    Reset(u32, u32),
+    /// Send a command without data
+    Cmd(C),
+    /// Send a command followed by its data
+    CmdWithData(C, &'a [u8]),
+    /// Send the same data byte the given number of times
+    DataXTimes(u8, u32),
+    /// Sleep for the given number of µs
+    Delay(u32),
+    /// Wait until the controller isn't busy anymore
+    WaitUntilIdle,
+}
+
+/// How a controller reports that it is busy
+#[derive(Clone, Copy)]
+pub(crate) struct Busy<C: Command> {
+    /// The BUSY line is low while the controller is busy
+    pub(crate) is_busy_low: bool,
+    /// Command which has to be sent to refresh the BUSY line, if any
+    pub(crate) status_command: Option<C>,
+}
@@ -0,0 +1,700 @@
+//! Host-side simulation of the display controllers
+//!
+//! [`Simulator`] hands out SPI, CS, BUSY, DC and RST handles which can be passed to any driver
+//! instead of real hardware. They decode the command stream the way the controller would and
+//! keep a virtual panel, so tests can assert what would actually be shown after a refresh
+//! instead of matching exact bytes.
+//!
+//! Two command sets are understood:
+//!
+//! - [`Controller::Uc81xx`]: `DataStartTransmission1/2` (0x10/0x13), `DisplayRefresh` (0x12),
+//!   `ResolutionSetting`/`TconResolution` (0x61), partial windows with `PartialWindow`,
+//!   `PartialIn` and `PartialOut` (0x90-0x92) and the windowed
+//!   `PartialDataStartTransmission1/2` (0x14/0x15) of the IL91874.
+//! - [`Controller::Ssd16xx`]: `WriteRam` (0x24) and the second RAM (0x26),
+//!   `SetRamX/YAddressStartEndPosition` (0x44/0x45), `SetRamX/YAddressCounter` (0x4E/0x4F),
+//!   `DataEntryModeSetting` (0x11), `SwReset` (0x12) and `MasterActivation` (0x20).
+//!
+//! Both controllers have two RAM planes. Plane 0 is the one written by `DataStartTransmission1`
+//! or `WriteRam`, plane 1 the one written by `DataStartTransmission2` or 0x26. What a plane
+//! means depends on the panel: black/white panels with an UC81xx controller show plane 1,
+//! tricolor panels use plane 0 for black/white and plane 1 for the chromatic color.
+//!
+//! Planes hold one bit per pixel, unless set otherwise with
+//! [`bits_per_pixel`](Simulator::bits_per_pixel).
+//!
+//! A UC81xx `PartialWindow` which doesn't start and end on whole channels of 8 pixels panics, as
+//! the controller ignores the low bits of its horizontal positions.
+//!
+//! # Example
+//!
+//!```rust
+//!# fn main() -> Result<(), epd_waveshare::prelude::EpdError<core::convert::Infallible, core::convert::Infallible>> {
+//!use epd_waveshare::{epd4in2::*, prelude::*, simulator::*};
+//!
+//!let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+//!let mut spi = sim.spi();
+//!let mut delay = SimDelay;
+//!let mut epd = Epd4in2::new(&mut spi, sim.cs(), sim.busy(), sim.dc(), sim.rst(), &mut delay, None)?;
+//!
+//!let display = Display4in2::default();
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!assert_eq!(sim.refreshes(), 1);
+//!assert_eq!(sim.shown(1), display.buffer());
+//!# Ok(())
+//!# }
+//!```
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_hal::{
+    blocking::{delay::DelayUs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::vec;
+use std::vec::Vec;
+
+/// The command set to simulate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    /// UltraChip UC81xx style controllers (IL0373, UC8176, UC8179, ...)
+    Uc81xx,
+    /// Solomon SSD16xx style controllers (SSD1608, SSD1675, SSD1680, ...)
+    Ssd16xx,
+}
+
+/// A simulated controller with its panel
+///
+/// Cloning it gives another handle to the same panel.
+#[derive(Clone)]
+pub struct Simulator {
+    panel: Rc<RefCell<Panel>>,
+}
+
+impl Simulator {
+    /// Creates a simulated panel of `width` x `height` pixels
+    pub fn new(controller: Controller, width: u32, height: u32) -> Self {
+        Simulator {
+            panel: Rc::new(RefCell::new(Panel::new(controller, width, height))),
+        }
+    }
+
+    /// The SPI bus of the controller
+    pub fn spi(&self) -> SimSpi {
+        SimSpi {
+            panel: self.panel.clone(),
+        }
+    }
+
+    /// Makes RAM plane `plane` hold `bits` bits per pixel instead of one (UC81xx only)
+    ///
+    /// E.g. 2 for the black plane of the Epd 1in54b or 4 for the 7-color Epd 5in65f. Windows are
+    /// still given in pixels and converted to bytes with this.
+    pub fn bits_per_pixel(self, plane: usize, bits: usize) -> Self {
+        {
+            let mut panel = self.panel.borrow_mut();
+            panel.bits_per_pixel[plane] = bits;
+            let len = panel.line_bytes(plane) * panel.height as usize;
+            panel.ram[plane] = vec![0; len];
+            panel.shown[plane] = vec![0; len];
+        }
+        self
+    }
+
+    /// The chip select pin
+    pub fn cs(&self) -> SimPin {
+        self.pin(PinRole::Cs)
+    }
+
+    /// The BUSY pin, idle unless set with [`set_busy`](Simulator::set_busy)
+    pub fn busy(&self) -> SimPin {
+        self.pin(PinRole::Busy)
+    }
+
+    /// The data/command pin
+    pub fn dc(&self) -> SimPin {
+        self.pin(PinRole::Dc)
+    }
+
+    /// The reset pin
+    pub fn rst(&self) -> SimPin {
+        self.pin(PinRole::Rst)
+    }
+
+    fn pin(&self, role: PinRole) -> SimPin {
+        SimPin {
+            panel: self.panel.clone(),
+            role,
+        }
+    }
+
+    /// Keeps the BUSY pin at its busy level, e.g. to test busy timeouts
+    pub fn set_busy(&self, busy: bool) {
+        self.panel.borrow_mut().busy = busy;
+    }
+
+    /// Number of refreshes (`DisplayRefresh` or `MasterActivation`) so far
+    pub fn refreshes(&self) -> usize {
+        self.panel.borrow_mut().flush().refreshes
+    }
+
+    /// Number of hardware resets so far
+    pub fn resets(&self) -> usize {
+        self.panel.borrow().resets
+    }
+
+    /// The resolution last sent with `ResolutionSetting`/`TconResolution` (UC81xx only)
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        self.panel.borrow_mut().flush().resolution
+    }
+
+    /// The parameters sent with the last `command`, e.g. to check which LUT is loaded
+    ///
+    /// RAM writes aren't recorded, use [`ram`](Simulator::ram) for them.
+    pub fn params(&self, command: u8) -> Option<Vec<u8>> {
+        self.panel
+            .borrow_mut()
+            .flush()
+            .params_log
+            .get(&command)
+            .cloned()
+    }
+
+    /// What the panel showed after the last refresh, for RAM plane 0 or 1
+    ///
+    /// The layout is the same as the buffer of a [`Display`](crate::graphics::Display): one bit
+    /// per pixel (or the bits set with [`bits_per_pixel`](Simulator::bits_per_pixel)), rows
+    /// padded to full bytes.
+    pub fn shown(&self, plane: usize) -> Vec<u8> {
+        self.panel.borrow_mut().flush().shown[plane].clone()
+    }
+
+    /// The current content of RAM plane 0 or 1, which isn't necessarily shown yet
+    pub fn ram(&self, plane: usize) -> Vec<u8> {
+        self.panel.borrow_mut().flush().ram[plane].clone()
+    }
+
+    /// Whether any bit of pixel (`x`, `y`) was set in RAM plane `plane` at the last refresh
+    pub fn shown_pixel(&self, plane: usize, x: u32, y: u32) -> bool {
+        self.shown_bits(plane, x, y) != 0
+    }
+
+    /// The bits of pixel (`x`, `y`) in RAM plane `plane` at the last refresh
+    pub fn shown_bits(&self, plane: usize, x: u32, y: u32) -> u8 {
+        let panel = self.panel.borrow();
+        let bits = panel.bits_per_pixel[plane];
+        let bit = x as usize * bits;
+        let byte = panel.shown[plane][y as usize * panel.line_bytes(plane) + bit / 8];
+        let shift = 8 - bits - bit % 8;
+        (byte >> shift) & (0xFF >> (8 - bits))
+    }
+}
+
+/// Simulated SPI bus, see [`Simulator::spi`]
+pub struct SimSpi {
+    panel: Rc<RefCell<Panel>>,
+}
+
+impl Write<u8> for SimSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut panel = self.panel.borrow_mut();
+        for &byte in words {
+            if panel.dc_high {
+                panel.data(byte);
+            } else {
+                panel.command(byte);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PinRole {
+    Cs,
+    Busy,
+    Dc,
+    Rst,
+}
+
+/// Simulated CS, BUSY, DC or RST pin, see [`Simulator`]
+pub struct SimPin {
+    panel: Rc<RefCell<Panel>>,
+    role: PinRole,
+}
+
+impl OutputPin for SimPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+impl SimPin {
+    fn set(&mut self, high: bool) {
+        let mut panel = self.panel.borrow_mut();
+        match self.role {
+            PinRole::Dc => panel.dc_high = high,
+            PinRole::Rst if !high => panel.reset(),
+            _ => {}
+        }
+    }
+}
+
+impl InputPin for SimPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let panel = self.panel.borrow();
+        if self.role != PinRole::Busy {
+            return Ok(false);
+        }
+        // UC81xx pull BUSY low while busy, SSD16xx pull it high
+        let busy_high = panel.controller == Controller::Ssd16xx;
+        Ok(panel.busy == busy_high)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// A delay which doesn't wait, as the simulated controller is never busy on its own
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimDelay;
+
+impl DelayUs<u32> for SimDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+// RAM window, in bytes horizontally and rows vertically, except for the UC81xx partial window
+// which is kept in pixels until a plane is written
+#[derive(Clone, Copy)]
+struct Window {
+    x_start: usize,
+    x_end: usize,
+    y_start: usize,
+    y_end: usize,
+}
+
+struct Panel {
+    controller: Controller,
+    width: u32,
+    height: u32,
+    bits_per_pixel: [usize; 2],
+    ram: [Vec<u8>; 2],
+    shown: [Vec<u8>; 2],
+    busy: bool,
+    dc_high: bool,
+    command: Option<u8>,
+    params: Vec<u8>,
+    // all parameters sent with each command since it was last sent
+    params_log: HashMap<u8, Vec<u8>>,
+    window: Window,
+    x: usize,
+    y: usize,
+    // SSD16xx data entry mode: bit 0 increments x, bit 1 increments y
+    entry_mode: u8,
+    // UC81xx partial mode and window
+    partial: bool,
+    partial_window: Window,
+    resolution: Option<(u32, u32)>,
+    refreshes: usize,
+    resets: usize,
+}
+
+impl Panel {
+    fn new(controller: Controller, width: u32, height: u32) -> Self {
+        let len = crate::buffer_len(width as usize, height as usize);
+        let mut panel = Panel {
+            controller,
+            width,
+            height,
+            bits_per_pixel: [1, 1],
+            ram: [vec![0; len], vec![0; len]],
+            shown: [vec![0; len], vec![0; len]],
+            busy: false,
+            dc_high: false,
+            command: None,
+            params: Vec::new(),
+            params_log: HashMap::new(),
+            window: Window {
+                x_start: 0,
+                x_end: 0,
+                y_start: 0,
+                y_end: 0,
+            },
+            x: 0,
+            y: 0,
+            entry_mode: 0x03,
+            partial: false,
+            partial_window: Window {
+                x_start: 0,
+                x_end: 0,
+                y_start: 0,
+                y_end: 0,
+            },
+            resolution: None,
+            refreshes: 0,
+            resets: 0,
+        };
+        panel.reset_registers();
+        panel.resets = 0;
+        panel
+    }
+
+    fn line_bytes(&self, plane: usize) -> usize {
+        crate::buffer_len(self.width as usize * self.bits_per_pixel[plane], 1)
+    }
+
+    fn full_window(&self) -> Window {
+        Window {
+            x_start: 0,
+            x_end: self.width as usize - 1,
+            y_start: 0,
+            y_end: self.height as usize - 1,
+        }
+    }
+
+    // converts a window in pixels to bytes of `plane` and moves the address counter to its start
+    fn start_transmission(&mut self, plane: usize, pixels: Window) {
+        let bits = self.bits_per_pixel[plane];
+        self.window = Window {
+            x_start: pixels.x_start * bits / 8,
+            x_end: ((pixels.x_end + 1) * bits).div_ceil(8) - 1,
+            ..pixels
+        };
+        self.x = self.window.x_start;
+        self.y = self.window.y_start;
+    }
+
+    fn reset(&mut self) {
+        self.resets += 1;
+        self.reset_registers();
+    }
+
+    fn reset_registers(&mut self) {
+        self.command = None;
+        self.params.clear();
+        self.start_transmission(0, self.full_window());
+        self.partial_window = self.full_window();
+        self.partial = false;
+        self.entry_mode = 0x03;
+        self.x = 0;
+        self.y = 0;
+    }
+
+    fn command(&mut self, command: u8) {
+        self.flush();
+        self.command = Some(command);
+        self.params_log.insert(command, Vec::new());
+        match (self.controller, command) {
+            // DataStartTransmission1/2 start at the top left of the (partial) window
+            (Controller::Uc81xx, 0x10 | 0x13) => {
+                let window = if self.partial {
+                    self.partial_window
+                } else {
+                    self.full_window()
+                };
+                self.start_transmission(Self::plane(command), window);
+            }
+            (Controller::Uc81xx, 0x12) | (Controller::Ssd16xx, 0x20) => self.refresh(),
+            (Controller::Uc81xx, 0x91) => self.partial = true,
+            (Controller::Uc81xx, 0x92) => self.partial = false,
+            (Controller::Ssd16xx, 0x12) => self.reset_registers(),
+            _ => {}
+        }
+    }
+
+    // RAM plane written by a UC81xx data transmission command
+    fn plane(command: u8) -> usize {
+        match command {
+            0x10 | 0x14 => 0,
+            _ => 1,
+        }
+    }
+
+    fn data(&mut self, byte: u8) {
+        match (self.controller, self.command) {
+            // PartialDataStartTransmission1/2 start with the window: x, y, width and height in
+            // two bytes each
+            (Controller::Uc81xx, Some(command @ (0x14 | 0x15))) if self.params.len() < 8 => {
+                self.params.push(byte);
+                if self.params.len() == 8 {
+                    let p = |i: usize| (self.params[i] as usize) << 8 | self.params[i + 1] as usize;
+                    let (x, y) = (p(0), p(2));
+                    let window = Window {
+                        x_start: x,
+                        x_end: x + p(4) - 1,
+                        y_start: y,
+                        y_end: y + p(6) - 1,
+                    };
+                    self.start_transmission(Self::plane(command), window);
+                }
+            }
+            (Controller::Uc81xx, Some(0x10 | 0x14)) | (Controller::Ssd16xx, Some(0x24)) => {
+                self.write_ram(0, byte)
+            }
+            (Controller::Uc81xx, Some(0x13 | 0x15)) | (Controller::Ssd16xx, Some(0x26)) => {
+                self.write_ram(1, byte)
+            }
+            _ => self.params.push(byte),
+        }
+    }
+
+    // applies the parameters of the current command once they are complete
+    fn flush(&mut self) -> &mut Self {
+        let params = core::mem::take(&mut self.params);
+        if let Some(log) = self
+            .command
+            .and_then(|command| self.params_log.get_mut(&command))
+        {
+            log.extend_from_slice(&params);
+        }
+        let p = |i: usize| params[i] as usize;
+        match (self.controller, self.command, params.len()) {
+            (Controller::Uc81xx, Some(0x61), 3) => {
+                self.resolution = Some((p(0) as u32, (p(1) << 8 | p(2)) as u32))
+            }
+            (Controller::Uc81xx, Some(0x61), 4) => {
+                self.resolution = Some(((p(0) << 8 | p(1)) as u32, (p(2) << 8 | p(3)) as u32))
+            }
+            // PartialWindow with one byte per horizontal and two per vertical position
+            (Controller::Uc81xx, Some(0x90), 7) => self.set_partial_window(Window {
+                x_start: p(0),
+                x_end: p(1),
+                y_start: p(2) << 8 | p(3),
+                y_end: p(4) << 8 | p(5),
+            }),
+            // PartialWindow with two bytes per position
+            (Controller::Uc81xx, Some(0x90), 9) => self.set_partial_window(Window {
+                x_start: p(0) << 8 | p(1),
+                x_end: p(2) << 8 | p(3),
+                y_start: p(4) << 8 | p(5),
+                y_end: p(6) << 8 | p(7),
+            }),
+            (Controller::Ssd16xx, Some(0x11), 1) => self.entry_mode = params[0],
+            // x is given in bytes by small controllers and in pixels by large ones
+            (Controller::Ssd16xx, Some(0x44), 2) => {
+                self.window.x_start = p(0);
+                self.window.x_end = p(1);
+            }
+            (Controller::Ssd16xx, Some(0x44), 4) => {
+                self.window.x_start = (p(1) << 8 | p(0)) / 8;
+                self.window.x_end = (p(3) << 8 | p(2)) / 8;
+            }
+            (Controller::Ssd16xx, Some(0x45), 4) => {
+                self.window.y_start = p(1) << 8 | p(0);
+                self.window.y_end = p(3) << 8 | p(2);
+            }
+            (Controller::Ssd16xx, Some(0x4E), 1) => self.x = p(0),
+            (Controller::Ssd16xx, Some(0x4E), 2) => self.x = (p(1) << 8 | p(0)) / 8,
+            (Controller::Ssd16xx, Some(0x4F), 2) => self.y = p(1) << 8 | p(0),
+            _ => {}
+        }
+        self
+    }
+
+    // The controller ignores the low 3 bits of the horizontal start and end (HRST[2:0] are 0,
+    // HRED[2:0] are 1), so windows which don't cover whole channels of 8 pixels would show up
+    // elsewhere on a real panel
+    fn set_partial_window(&mut self, window: Window) {
+        assert!(
+            window.x_start.is_multiple_of(8) && window.x_end % 8 == 7,
+            "PartialWindow from {} to {} isn't aligned to 8 pixels",
+            window.x_start,
+            window.x_end,
+        );
+        self.partial_window = window;
+    }
+
+    fn write_ram(&mut self, plane: usize, byte: u8) {
+        let line_bytes = self.line_bytes(plane);
+        if self.x < line_bytes && self.y < self.height as usize {
+            self.ram[plane][self.y * line_bytes + self.x] = byte;
+        }
+        self.advance();
+    }
+
+    // moves the address counter to the next byte, wrapping around inside the window. The window
+    // may be given with its start above its end, the counter always runs from one edge to the
+    // other in the direction of the data entry mode.
+    fn advance(&mut self) {
+        let Window {
+            x_start,
+            x_end,
+            y_start,
+            y_end,
+        } = self.window;
+        let x_inc = self.entry_mode & 0x01 != 0;
+        let y_inc = self.entry_mode & 0x02 != 0;
+        let (x_first, x_last) = edges(x_start, x_end, x_inc);
+        if self.x == x_last {
+            self.x = x_first;
+            let (y_first, y_last) = edges(y_start, y_end, y_inc);
+            self.y = if self.y == y_last {
+                y_first
+            } else if y_inc {
+                self.y + 1
+            } else {
+                self.y.wrapping_sub(1)
+            };
+        } else if x_inc {
+            self.x += 1;
+        } else {
+            self.x = self.x.wrapping_sub(1);
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.refreshes += 1;
+        self.shown = self.ram.clone();
+    }
+}
+
+// first and last address of a window edge when counting up or down
+fn edges(start: usize, end: usize, increment: bool) -> (usize, usize) {
+    let (low, high) = (start.min(end), start.max(end));
+    if increment {
+        (low, high)
+    } else {
+        (high, low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn uc81xx_full_frame() {
+        use crate::epd4in2::*;
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let buffer: Vec<u8> = (0..WIDTH * HEIGHT / 8).map(|i| i as u8).collect();
+        epd.update_frame(&mut spi, &buffer, &mut SimDelay).unwrap();
+        assert_eq!(sim.refreshes(), 0);
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert_eq!(sim.resets(), 1);
+        assert_eq!(sim.refreshes(), 1);
+        assert_eq!(sim.resolution(), Some((WIDTH, HEIGHT)));
+        assert_eq!(sim.shown(1), buffer);
+    }
+
+    #[test]
+    fn uc81xx_partial_window() {
+        use crate::epd4in2::*;
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd4in2::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        epd.update_partial_frame(&mut spi, &mut SimDelay, &[0xFF; 2 * 3], 16, 8, 16, 3)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert!(sim.shown_pixel(1, 16, 8));
+        assert!(sim.shown_pixel(1, 31, 10));
+        assert!(!sim.shown_pixel(1, 15, 8));
+        assert!(!sim.shown_pixel(1, 32, 8));
+        assert!(!sim.shown_pixel(1, 16, 11));
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't aligned to 8 pixels")]
+    fn uc81xx_unaligned_partial_window() {
+        let sim = Simulator::new(Controller::Uc81xx, 64, 8);
+        let (mut spi, mut dc) = (sim.spi(), sim.dc());
+        dc.set_low().unwrap();
+        spi.write(&[0x90]).unwrap();
+        dc.set_high().unwrap();
+        // from x = 6 to 13
+        spi.write(&[0x00, 0x06, 0x00, 0x0D, 0x00, 0x00, 0x00, 0x07, 0x00])
+            .unwrap();
+        dc.set_low().unwrap();
+        spi.write(&[0x91]).unwrap();
+    }
+
+    #[test]
+    fn uc81xx_tricolor_planes() {
+        use crate::epd2in9bc::*;
+
+        let sim = Simulator::new(Controller::Uc81xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in9bc::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let black = vec![0x0F; (WIDTH * HEIGHT / 8) as usize];
+        let chromatic = vec![0xF0; (WIDTH * HEIGHT / 8) as usize];
+        epd.update_color_frame(&mut spi, &mut SimDelay, &black, &chromatic)
+            .unwrap();
+        epd.display_frame(&mut spi, &mut SimDelay).unwrap();
+
+        assert_eq!(sim.shown(0), black);
+        assert_eq!(sim.shown(1), chromatic);
+    }
+
+    #[test]
+    fn ssd16xx_full_frame() {
+        use crate::epd2in9_v2::*;
+
+        let sim = Simulator::new(Controller::Ssd16xx, WIDTH, HEIGHT);
+        let mut spi = sim.spi();
+        let mut epd = Epd2in9::new(
+            &mut spi,
+            sim.cs(),
+            sim.busy(),
+            sim.dc(),
+            sim.rst(),
+            &mut SimDelay,
+            None,
+        )
+        .unwrap();
+        let buffer: Vec<u8> = (0..WIDTH * HEIGHT / 8).map(|i| (i * 7) as u8).collect();
+        epd.update_and_display_frame(&mut spi, &buffer, &mut SimDelay)
+            .unwrap();
+
+        assert_eq!(sim.refreshes(), 1);
+        assert_eq!(sim.shown(0), buffer);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::EpdError;
 use core::marker::Sized;
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
@@ -11,18 +12,93 @@ pub(crate) trait Command: Copy {
 }
 
 /// Seperates the different LUT for the Display Refresh process
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
 pub enum RefreshLut {
     /// The "normal" full Lookuptable for the Refresh-Sequence
+    #[default]
     Full,
     /// The quick LUT where not the full refresh sequence is followed.
     /// This might lead to some
     Quick,
 }
 
-impl Default for RefreshLut {
-    fn default() -> Self {
-        RefreshLut::Full
+/// Ways of refreshing the panel, selected with
+/// [`set_refresh_mode`](WaveshareDisplay::set_refresh_mode)
+///
+/// Not every display supports every mode, ask
+/// [`supports_refresh_mode`](WaveshareDisplay::supports_refresh_mode) first.
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
+pub enum RefreshMode {
+    /// Full refresh with the clean waveform, flashing the whole panel to remove ghosting
+    #[default]
+    Full,
+    /// Refresh of the whole panel with a shorter waveform, flashing less at the cost of some
+    /// ghosting
+    FastFull,
+    /// Only drives the pixels that changed, without flashing. Run a [`Full`](RefreshMode::Full)
+    /// refresh every now and then to clear the ghosting.
+    Partial,
+    /// 4-gray mode, see [`WaveshareGrayscaleDisplay`]
+    Grayscale,
+}
+
+/// Features of a display, see [`WaveshareDisplay::CAPABILITIES`]
+///
+/// Lets generic code pick features without knowing the display model:
+///
+///```rust
+///# use epd_waveshare::prelude::*;
+///fn describe(capabilities: Capabilities) -> &'static str {
+///    if capabilities.chromatic {
+///        "three colors"
+///    } else if capabilities.supports_refresh_mode(RefreshMode::Grayscale) {
+///        "4 grays"
+///    } else {
+///        "black and white"
+///    }
+///}
+///
+///assert_eq!(describe(Capabilities::BASIC), "black and white");
+///```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Windows can be written with [update_partial_frame](WaveshareDisplay::update_partial_frame)
+    pub partial_update: bool,
+    /// Supports [`RefreshMode::Partial`]
+    pub partial_refresh: bool,
+    /// Supports [`RefreshMode::FastFull`]
+    pub fast_full_refresh: bool,
+    /// Supports [`RefreshMode::Grayscale`] and implements [`WaveshareGrayscaleDisplay`]
+    pub grayscale: bool,
+    /// Has a chromatic layer and implements [`WaveshareThreeColorDisplay`]
+    pub chromatic: bool,
+    /// Implements [`QuickRefresh`]
+    pub quick_refresh: bool,
+    /// Implements [`WaveshareCustomLut`]
+    pub custom_lut: bool,
+}
+
+impl Capabilities {
+    /// Only full frames and full refreshes
+    pub const BASIC: Capabilities = Capabilities {
+        partial_update: false,
+        partial_refresh: false,
+        fast_full_refresh: false,
+        grayscale: false,
+        chromatic: false,
+        quick_refresh: false,
+        custom_lut: false,
+    };
+
+    /// Checks whether `mode` can be selected with
+    /// [set_refresh_mode](WaveshareDisplay::set_refresh_mode)
+    pub const fn supports_refresh_mode(&self, mode: RefreshMode) -> bool {
+        match mode {
+            RefreshMode::Full => true,
+            RefreshMode::FastFull => self.fast_full_refresh,
+            RefreshMode::Partial => self.partial_refresh,
+            RefreshMode::Grayscale => self.grayscale,
+        }
     }
 }
 
@@ -30,9 +106,9 @@ pub(crate) trait InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// This initialises the EPD and powers it up
@@ -45,7 +121,11 @@ where
     /// This function calls [reset](WaveshareDisplay::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 }
 
 /// Functions to interact with three color panels
@@ -54,9 +134,9 @@ pub trait WaveshareThreeColorDisplay<SPI, CS, BUSY, DC, RST, DELAY>:
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// Transmit data to the SRAM of the EPD
@@ -68,7 +148,7 @@ where
         delay: &mut DELAY,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Update only the black/white data of the display.
     ///
@@ -78,7 +158,7 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         black: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Update only the chromatic data of the display.
     ///
@@ -89,7 +169,127 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Transmit a window of both layers to the SRAM of the EPD
+    ///
+    /// (x,y) is the top left corner, `x` and `width` need to be multiples of 8.
+    /// Both buffers need to be of size: width / 8 * height !
+    ///
+    /// The window is shown with the next [`display_frame`](WaveshareDisplay::display_frame).
+    /// [`update_partial_frame`](WaveshareDisplay::update_partial_frame) of three color displays
+    /// only takes the black/white window and clears the chromatic one.
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.update_partial_achromatic_frame(spi, delay, black, x, y, width, height)?;
+        self.update_partial_chromatic_frame(spi, delay, chromatic, x, y, width, height)
+    }
+
+    /// Update only the black/white data of a window of the display.
+    ///
+    /// This must be finished by calling `update_partial_chromatic_frame` for the same window.
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        black: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Update only the chromatic data of a window of the display.
+    ///
+    /// This should be preceded by a call to `update_partial_achromatic_frame`.
+    /// This data takes precedence over the black/white data.
+    #[allow(clippy::too_many_arguments)]
+    fn update_partial_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        chromatic: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+}
+
+/// Functions to use panels in 4-gray mode
+///
+/// Gray frames are laid out like the buffer of a [`Display`](crate::graphics::Display) of
+/// [`Gray2`](crate::color::Gray2): the high bits of all pixels first, then the low bits.
+/// They are shown with [`display_frame`](WaveshareDisplay::display_frame).
+pub trait WaveshareGrayscaleDisplay<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    /// Initialises the EPD in 4-gray mode and loads the grayscale LUT
+    ///
+    /// Use [`wake_up`](WaveshareDisplay::wake_up) to go back to black and white mode.
+    fn init_grayscale(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Transmit a full 4-gray frame to the SRAM of the EPD
+    ///
+    /// The first half of `buffer` holds the high bit of every pixel and goes to the first RAM
+    /// (`DataStartTransmission1` or `WriteRam`), the second half holds the low bits and goes to
+    /// the second RAM (`DataStartTransmission2` or `WriteRam2`). With the grayscale LUT a luma
+    /// of 0 is shown as black and 3 as white.
+    fn update_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+}
+
+/// Functions to load user-defined waveforms
+///
+/// The table type depends on the controller, see the [`lut`](crate::lut) module. A loaded table
+/// is used by all following refreshes until [`set_lut`](WaveshareDisplay::set_lut) or
+/// [`wake_up`](WaveshareDisplay::wake_up) load one of the built-in tables again.
+pub trait WaveshareCustomLut<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    /// Look-up table model of the controller
+    type Lut;
+
+    /// Uploads `lut` to the controller
+    fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        lut: &Self::Lut,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 }
 
 /// All the functions to interact with the EPDs
@@ -100,7 +300,7 @@ where
 ///
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -140,13 +340,68 @@ pub trait WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// The Color Type used by the Display
     type DisplayColor;
+
+    /// The full size [`Display`](crate::graphics::Display) buffer matching the display
+    ///
+    /// Together with the constants below, this allows drawing code that works with any display:
+    ///
+    ///```rust, no_run
+    ///# use embedded_hal::{blocking::{delay::DelayUs, spi::Write}, digital::v2::*};
+    ///use epd_waveshare::prelude::*;
+    ///
+    ///fn render<SPI, CS, BUSY, DC, RST, DELAY, E>(
+    ///    epd: &mut E,
+    ///    spi: &mut SPI,
+    ///    delay: &mut DELAY,
+    ///) -> Result<(), EpdError<SPI::Error, CS::Error>>
+    ///where
+    ///    SPI: Write<u8>,
+    ///    CS: OutputPin,
+    ///    BUSY: InputPin<Error = CS::Error>,
+    ///    DC: OutputPin<Error = CS::Error>,
+    ///    RST: OutputPin<Error = CS::Error>,
+    ///    DELAY: DelayUs<u32>,
+    ///    E: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    ///{
+    ///    let mut display = E::Display::default();
+    ///    if E::HEIGHT > E::WIDTH {
+    ///        display.set_rotation(DisplayRotation::Rotate90);
+    ///    }
+    ///    // Draw on the display here
+    ///
+    ///    if E::CAPABILITIES.partial_refresh {
+    ///        epd.set_refresh_mode(spi, delay, RefreshMode::Partial)?;
+    ///    }
+    ///    epd.update_and_display_frame(spi, display.buffer(), delay)
+    ///}
+    ///```
+    #[cfg(feature = "graphics")]
+    type Display: crate::graphics::DisplayBuffer;
+
+    /// Width of the display in pixels
+    const WIDTH: u32;
+
+    /// Height of the display in pixels
+    const HEIGHT: u32;
+
+    /// Length of a full frame buffer, as used by [`Display`](WaveshareDisplay::Display)
+    ///
+    /// Three color displays count both planes.
+    const BUFFER_LEN: usize;
+
+    /// Whether the BUSY line is low while the display is busy
+    const IS_BUSY_LOW: bool;
+
+    /// Features the display supports
+    const CAPABILITIES: Capabilities;
+
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
     /// `delay_us` is the number of us the idle loop should sleep on.
@@ -162,19 +417,27 @@ where
         rst: RST,
         delay: &mut DELAY,
         delay_us: Option<u32>,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, EpdError<SPI::Error, CS::Error>>
     where
         Self: Sized;
 
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn sleep(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Wakes the device up from sleep
     ///
     /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
     fn set_background_color(&mut self, color: Self::DisplayColor);
@@ -182,6 +445,43 @@ where
     /// Get current background color
     fn background_color(&self) -> &Self::DisplayColor;
 
+    /// Sets how many `delay_us` ticks to wait for the busy line before giving up with
+    /// [`EpdError::BusyTimeout`]
+    ///
+    /// `None` (the default) waits forever.
+    fn set_busy_timeout(&mut self, max_ticks: Option<u32>);
+
+    /// Sets the maximum number of bytes sent in a single SPI write
+    ///
+    /// Frames are sent as one transfer, split up into writes of at most `max_bytes` while CS
+    /// stays low. Defaults to 4096 on Linux, whose spidev rejects larger transfers, and to no
+    /// limit everywhere else.
+    fn set_max_transfer_size(&mut self, max_bytes: Option<usize>);
+
+    /// Runs `operation` and, if it fails with [`EpdError::BusyTimeout`], recovers the
+    /// display with a hardware reset and re-initialisation (see [wake_up](WaveshareDisplay::wake_up))
+    /// before retrying `operation` once
+    ///
+    /// This only makes sense after a timeout was configured with
+    /// [set_busy_timeout](WaveshareDisplay::set_busy_timeout).
+    fn retry_after_reset<T, F>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mut operation: F,
+    ) -> Result<T, EpdError<SPI::Error, CS::Error>>
+    where
+        F: FnMut(&mut Self, &mut SPI, &mut DELAY) -> Result<T, EpdError<SPI::Error, CS::Error>>,
+    {
+        match operation(self, spi, delay) {
+            Err(EpdError::BusyTimeout) => {
+                self.wake_up(spi, delay)?;
+                operation(self, spi, delay)
+            }
+            result => result,
+        }
+    }
+
     /// Get the width of the display
     fn width(&self) -> u32;
 
@@ -194,7 +494,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Transmits partial data to the SRAM of the EPD
     ///
@@ -211,12 +511,45 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Starts displaying the frame data from SRAM without waiting for the refresh to finish
+    ///
+    /// The MCU can sleep in the meantime, e.g. until the BUSY line changes. Once
+    /// [is_busy](WaveshareDisplay::is_busy) returns false, call
+    /// [finish_refresh](WaveshareDisplay::finish_refresh).
+    fn start_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Completes a refresh started with [start_refresh](WaveshareDisplay::start_refresh)
+    ///
+    /// Waits until the device isn't busy anymore and runs the steps the display needs after a
+    /// refresh, like powering the panel off.
+    fn finish_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        self.wait_until_idle(spi, delay)
+    }
+
+    /// Checks whether the device is still busy, e.g. with a refresh
+    ///
+    /// Reads the BUSY line with the polarity of the display. Controllers which only update BUSY
+    /// when asked for their status are sent the status command first.
+    fn is_busy(&mut self, spi: &mut SPI) -> Result<bool, EpdError<SPI::Error, CS::Error>>;
 
     /// Provide a combined update&display and save some time (skipping a busy check in between)
     fn update_and_display_frame(
@@ -224,12 +557,16 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -244,12 +581,40 @@ where
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Checks whether the display can refresh in `mode`
+    ///
+    /// Every display supports [`RefreshMode::Full`], the others are listed in
+    /// [`CAPABILITIES`](WaveshareDisplay::CAPABILITIES).
+    fn supports_refresh_mode(&self, mode: RefreshMode) -> bool {
+        Self::CAPABILITIES.supports_refresh_mode(mode)
+    }
+
+    /// Selects how the following refreshes update the panel
+    ///
+    /// Returns [`EpdError::Unsupported`] for modes
+    /// [supports_refresh_mode](WaveshareDisplay::supports_refresh_mode) doesn't report.
+    fn set_refresh_mode(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        mode: RefreshMode,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        match mode {
+            RefreshMode::Full => self.set_lut(spi, delay, Some(RefreshLut::Full)),
+            _ => Err(EpdError::Unsupported),
+        }
+    }
 
     /// Wait until the display has stopped processing data
     ///
     /// You can call this to make sure a frame is displayed before goin further
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -263,7 +628,7 @@ where
 /// Example:
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::prelude::EpdError<MockError, MockError>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -301,9 +666,9 @@ pub trait QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
 where
     SPI: Write<u8>,
     CS: OutputPin,
-    BUSY: InputPin,
-    DC: OutputPin,
-    RST: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
     DELAY: DelayUs<u32>,
 {
     /// Updates the old frame.
@@ -312,7 +677,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -320,10 +685,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -331,7 +700,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Updates the old frame for a portion of the display.
     #[allow(clippy::too_many_arguments)]
@@ -344,7 +713,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Updates the new frame for a portion of the display.
     #[allow(clippy::too_many_arguments)]
@@ -357,7 +726,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
@@ -369,5 +738,56 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+}
+
+/// Writes a full frame to the controller RAM in several pieces instead of from one buffer
+///
+/// This is what [`BandRenderer`](crate::band::BandRenderer) uses to drive a display from a
+/// scratch buffer of a few rows.
+///
+/// A frame is sent in [`frame_passes`](WaveshareStreamingDisplay::frame_passes) passes over
+/// all rows, each started with [`begin_frame_pass`](WaveshareStreamingDisplay::begin_frame_pass)
+/// and filled with [`write_frame_rows`](WaveshareStreamingDisplay::write_frame_rows). Pass `n`
+/// contains color plane `n % BUFFER_COUNT` of the frame, so tricolor displays get their
+/// black/white plane first and their chromatic plane second.
+pub trait WaveshareStreamingDisplay<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin<Error = CS::Error>,
+    DC: OutputPin<Error = CS::Error>,
+    RST: OutputPin<Error = CS::Error>,
+    DELAY: DelayUs<u32>,
+{
+    /// Number of passes over the frame
+    fn frame_passes(&self) -> usize {
+        1
+    }
+
+    /// Prepares the controller RAM for pass `pass` of a full frame
+    fn begin_frame_pass(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        pass: usize,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Sends the next rows of the current pass, laid out like the buffer of
+    /// [`update_frame`](WaveshareDisplay::update_frame)
+    fn write_frame_rows(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>>;
+
+    /// Finishes the frame after the last pass, e.g. by clearing an unused chromatic plane
+    fn end_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), EpdError<SPI::Error, CS::Error>> {
+        Ok(())
+    }
 }
@@ -17,9 +17,11 @@ pub(crate) const LUT_PARTIAL_UPDATE: [u8; 30] =[
 ];
 
 #[cfg(feature = "type_a_alternative_faster_lut")]
+pub(crate) const LUT_FULL_UPDATE: [u8; 30] = LUT_FAST_FULL_UPDATE;
+
 #[rustfmt::skip]
 // Waveform from TeXiToi/il3820
-pub(crate) const LUT_FULL_UPDATE: [u8; 30] =[
+pub(crate) const LUT_FAST_FULL_UPDATE: [u8; 30] =[
     0x50, 0xAA, 0x55, 0xAA, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x1F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];